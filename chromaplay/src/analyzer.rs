@@ -0,0 +1,153 @@
+use crate::{
+    bands::{BandMapper, BandSettings},
+    AudioBuffer,
+};
+use rustfft::{num_complex::Complex, Fft, FftPlanner};
+use std::{f32::consts::PI, sync::Arc, time::Duration};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WindowFunction {
+    Rectangular,
+    Hann,
+    Hamming,
+    Blackman,
+}
+
+impl WindowFunction {
+    fn coefficients(self, len: usize) -> Vec<f32> {
+        let n = (len.max(2) - 1) as f32;
+
+        (0..len)
+            .map(|i| {
+                let x = 2.0 * PI * i as f32 / n;
+
+                match self {
+                    WindowFunction::Rectangular => 1.0,
+                    WindowFunction::Hann => 0.5 - 0.5 * x.cos(),
+                    WindowFunction::Hamming => 0.54 - 0.46 * x.cos(),
+                    WindowFunction::Blackman => 0.42 - 0.5 * x.cos() + 0.08 * (2.0 * x).cos(),
+                }
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnalyzerSettings {
+    /// Number of frames in each analysis window.
+    pub fft_size: usize,
+    /// Number of frames between two consecutive analysis windows.
+    pub hop: usize,
+    pub window: WindowFunction,
+    /// Number of output bands, usually `ParticleSettings::frequencies`.
+    pub bands: usize,
+    pub mapping: BandSettings,
+}
+
+impl Default for AnalyzerSettings {
+    fn default() -> Self {
+        Self {
+            fft_size: 2048,
+            hop: 512,
+            window: WindowFunction::Hann,
+            bands: 32,
+            mapping: BandSettings::default(),
+        }
+    }
+}
+
+/// Turns audio into per-band levels in the `0.0..=1.0` range.
+pub struct SpectrumAnalyzer {
+    settings: AnalyzerSettings,
+    fft: Arc<dyn Fft<f32>>,
+    window: Vec<f32>,
+    // scales magnitudes so that a full-scale sine reads as 1.0
+    normalization: f32,
+    spectrum: Vec<Complex<f32>>,
+    scratch: Vec<Complex<f32>>,
+    magnitudes: Vec<f32>,
+    mapper: BandMapper,
+    bands: Vec<f32>,
+}
+
+impl SpectrumAnalyzer {
+    pub fn new(settings: AnalyzerSettings) -> Self {
+        assert!(settings.fft_size >= 2, "fft size must be at least 2");
+        assert!(settings.hop > 0, "hop must be positive");
+        assert!(settings.bands > 0, "there must be at least one band");
+
+        let fft = FftPlanner::new().plan_fft_forward(settings.fft_size);
+        let window = settings.window.coefficients(settings.fft_size);
+        let normalization = 2.0 / window.iter().sum::<f32>();
+
+        Self {
+            spectrum: vec![Complex::default(); settings.fft_size],
+            scratch: vec![Complex::default(); fft.get_inplace_scratch_len()],
+            magnitudes: vec![0.0; settings.fft_size / 2 + 1],
+            bands: vec![0.0; settings.bands],
+            mapper: BandMapper::new(settings.mapping.clone(), settings.bands),
+            fft,
+            window,
+            normalization,
+            settings,
+        }
+    }
+
+    pub fn settings(&self) -> &AnalyzerSettings {
+        &self.settings
+    }
+
+    /// Center frequency of an FFT bin, in Hz.
+    pub fn bin_frequency(&self, bin: usize, sample_rate: u32) -> f32 {
+        bin as f32 * sample_rate as f32 / self.settings.fft_size as f32
+    }
+
+    /// Analyzes the window ending at `frame`, snapped down to the hop grid.
+    pub fn analyze(&mut self, buffer: &AudioBuffer, frame: usize) -> &[f32] {
+        let end = frame.min(buffer.frames()) / self.settings.hop * self.settings.hop;
+        let start = end as isize - self.settings.fft_size as isize;
+        let channels = buffer.channels() as usize;
+
+        // downmix to mono, zero-padding before the start of the buffer
+        for (i, (bin, w)) in self.spectrum.iter_mut().zip(&self.window).enumerate() {
+            let index = start + i as isize;
+            let sample = if index < 0 {
+                0.0
+            } else {
+                buffer.frame(index as usize).iter().sum::<f32>() / channels as f32
+            };
+
+            *bin = Complex::new(sample * w, 0.0);
+        }
+
+        self.fft
+            .process_with_scratch(&mut self.spectrum, &mut self.scratch);
+
+        for (magnitude, bin) in self.magnitudes.iter_mut().zip(&self.spectrum) {
+            *magnitude = (bin.norm() * self.normalization).min(1.0);
+        }
+
+        self.mapper
+            .map(&self.magnitudes, buffer.sample_rate(), &mut self.bands);
+
+        &self.bands
+    }
+
+    pub fn analyze_at(&mut self, buffer: &AudioBuffer, time: Duration) -> &[f32] {
+        self.analyze(buffer, buffer.frame_at(time))
+    }
+
+    /// Magnitude of each FFT bin from DC to Nyquist, from the last analysis.
+    pub fn magnitudes(&self) -> &[f32] {
+        &self.magnitudes
+    }
+
+    pub fn band_mapper(&self) -> &BandMapper {
+        &self.mapper
+    }
+
+    /// Band levels from the last analysis.
+    pub fn bands(&self) -> &[f32] {
+        &self.bands
+    }
+}
@@ -0,0 +1,189 @@
+use std::ops::Range;
+
+/// How band edges are spread between the minimum and maximum frequency.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FrequencyScale {
+    Linear,
+    /// Needs a positive minimum frequency.
+    Logarithmic,
+    Mel,
+    Bark,
+    /// Bands of `1/n` octave each, starting at the minimum frequency.
+    ///
+    /// The maximum frequency is ignored: it follows from the number of bands. Needs a positive
+    /// minimum frequency.
+    OctaveFraction(u32),
+}
+
+impl FrequencyScale {
+    fn is_logarithmic(self) -> bool {
+        matches!(
+            self,
+            FrequencyScale::Logarithmic | FrequencyScale::OctaveFraction(_)
+        )
+    }
+
+    fn warp(self, hz: f32) -> f32 {
+        match self {
+            FrequencyScale::Linear => hz,
+            FrequencyScale::Logarithmic | FrequencyScale::OctaveFraction(_) => hz.ln(),
+            FrequencyScale::Mel => 2595.0 * (1.0 + hz / 700.0).log10(),
+            // Traunmüller (1990)
+            FrequencyScale::Bark => 26.81 * hz / (1960.0 + hz) - 0.53,
+        }
+    }
+
+    fn unwarp(self, x: f32) -> f32 {
+        match self {
+            FrequencyScale::Linear => x,
+            FrequencyScale::Logarithmic | FrequencyScale::OctaveFraction(_) => x.exp(),
+            FrequencyScale::Mel => 700.0 * (10f32.powf(x / 2595.0) - 1.0),
+            FrequencyScale::Bark => 1960.0 * (x + 0.53) / (26.28 - x),
+        }
+    }
+}
+
+/// How the bins falling into a band are combined into one level.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Aggregation {
+    Peak,
+    Rms,
+    Mean,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BandSettings {
+    pub scale: FrequencyScale,
+    pub min_frequency: f32,
+    pub max_frequency: f32,
+    pub aggregation: Aggregation,
+}
+
+impl Default for BandSettings {
+    fn default() -> Self {
+        Self {
+            scale: FrequencyScale::Logarithmic,
+            min_frequency: 30.0,
+            max_frequency: 16000.0,
+            aggregation: Aggregation::Peak,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum BandSource {
+    Bins(Range<usize>),
+    // band narrower than a bin: sample the spectrum between two bins
+    Between(usize, f32),
+}
+
+/// Maps FFT bin magnitudes onto a fixed number of bands.
+#[derive(Debug, Clone)]
+pub struct BandMapper {
+    settings: BandSettings,
+    edges: Vec<f32>,
+    // bin layout for the last (bin count, sample rate) pair
+    layout: Option<(usize, u32)>,
+    sources: Vec<BandSource>,
+}
+
+impl BandMapper {
+    pub fn new(settings: BandSettings, bands: usize) -> Self {
+        assert!(bands > 0, "there must be at least one band");
+        assert!(
+            settings.min_frequency >= 0.0 && settings.min_frequency < settings.max_frequency,
+            "invalid frequency range"
+        );
+        assert!(
+            settings.min_frequency > 0.0 || !settings.scale.is_logarithmic(),
+            "logarithmic scales need a positive minimum frequency"
+        );
+
+        let edges = match settings.scale {
+            FrequencyScale::OctaveFraction(n) => (0..=bands)
+                .map(|i| settings.min_frequency * 2f32.powf(i as f32 / n.max(1) as f32))
+                .collect(),
+            scale => {
+                let lo = scale.warp(settings.min_frequency);
+                let hi = scale.warp(settings.max_frequency);
+
+                (0..=bands)
+                    .map(|i| scale.unwarp(lo + (hi - lo) * i as f32 / bands as f32))
+                    .collect()
+            }
+        };
+
+        Self {
+            settings,
+            edges,
+            layout: None,
+            sources: Vec::new(),
+        }
+    }
+
+    pub fn settings(&self) -> &BandSettings {
+        &self.settings
+    }
+
+    pub fn bands(&self) -> usize {
+        self.edges.len() - 1
+    }
+
+    /// Band boundaries in Hz, `bands() + 1` of them.
+    pub fn edges(&self) -> &[f32] {
+        &self.edges
+    }
+
+    /// Maps `magnitudes`, the bins from DC to Nyquist, onto `out`.
+    pub fn map(&mut self, magnitudes: &[f32], sample_rate: u32, out: &mut [f32]) {
+        if self.layout != Some((magnitudes.len(), sample_rate)) {
+            self.compute_layout(magnitudes.len(), sample_rate);
+        }
+
+        for (band, source) in out.iter_mut().zip(&self.sources) {
+            *band = match source {
+                BandSource::Bins(bins) => {
+                    let bins = &magnitudes[bins.clone()];
+
+                    match self.settings.aggregation {
+                        Aggregation::Peak => bins.iter().cloned().fold(0.0, f32::max),
+                        Aggregation::Mean => bins.iter().sum::<f32>() / bins.len() as f32,
+                        Aggregation::Rms => {
+                            (bins.iter().map(|m| m * m).sum::<f32>() / bins.len() as f32).sqrt()
+                        }
+                    }
+                }
+                &BandSource::Between(bin, t) => {
+                    let a = magnitudes[bin];
+                    let b = magnitudes[(bin + 1).min(magnitudes.len() - 1)];
+
+                    a + (b - a) * t
+                }
+            };
+        }
+    }
+
+    fn compute_layout(&mut self, bins: usize, sample_rate: u32) {
+        let nyquist = sample_rate as f32 / 2.0;
+        let bin_width = nyquist / (bins - 1).max(1) as f32;
+
+        self.sources = self
+            .edges
+            .windows(2)
+            .map(|edge| {
+                let lo = ((edge[0] / bin_width).ceil() as usize).min(bins);
+                let hi = ((edge[1] / bin_width).ceil() as usize).min(bins);
+
+                if lo < hi {
+                    BandSource::Bins(lo..hi)
+                } else {
+                    let center = ((edge[0] + edge[1]) / 2.0 / bin_width).min((bins - 1) as f32);
+
+                    BandSource::Between(center as usize, center.fract())
+                }
+            })
+            .collect();
+
+        self.layout = Some((bins, sample_rate));
+    }
+}
@@ -0,0 +1,79 @@
+use std::{fmt, io};
+
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+    Wav(hound::Error),
+    NoOutputDevice,
+    SupportedConfigs(cpal::SupportedStreamConfigsError),
+    DefaultConfig(cpal::DefaultStreamConfigError),
+    BuildStream(cpal::BuildStreamError),
+    PlayStream(cpal::PlayStreamError),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Io(e) => write!(f, "i/o error: {}", e),
+            Error::Wav(e) => write!(f, "wav error: {}", e),
+            Error::NoOutputDevice => write!(f, "no audio output device available"),
+            Error::SupportedConfigs(e) => write!(f, "couldn't query output configs: {}", e),
+            Error::DefaultConfig(e) => write!(f, "couldn't query default output config: {}", e),
+            Error::BuildStream(e) => write!(f, "couldn't open output stream: {}", e),
+            Error::PlayStream(e) => write!(f, "couldn't start output stream: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(e) => Some(e),
+            Error::Wav(e) => Some(e),
+            Error::NoOutputDevice => None,
+            Error::SupportedConfigs(e) => Some(e),
+            Error::DefaultConfig(e) => Some(e),
+            Error::BuildStream(e) => Some(e),
+            Error::PlayStream(e) => Some(e),
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+impl From<hound::Error> for Error {
+    fn from(e: hound::Error) -> Self {
+        match e {
+            hound::Error::IoError(e) => Error::Io(e),
+            e => Error::Wav(e),
+        }
+    }
+}
+
+impl From<cpal::SupportedStreamConfigsError> for Error {
+    fn from(e: cpal::SupportedStreamConfigsError) -> Self {
+        Error::SupportedConfigs(e)
+    }
+}
+
+impl From<cpal::DefaultStreamConfigError> for Error {
+    fn from(e: cpal::DefaultStreamConfigError) -> Self {
+        Error::DefaultConfig(e)
+    }
+}
+
+impl From<cpal::BuildStreamError> for Error {
+    fn from(e: cpal::BuildStreamError) -> Self {
+        Error::BuildStream(e)
+    }
+}
+
+impl From<cpal::PlayStreamError> for Error {
+    fn from(e: cpal::PlayStreamError) -> Self {
+        Error::PlayStream(e)
+    }
+}
@@ -0,0 +1,186 @@
+use std::{collections::VecDeque, time::Duration};
+
+/// Exponential smoothing time constants.
+///
+/// After one time constant, a level has covered ~63% of the way to its target.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Smoothing {
+    /// Used while the level rises.
+    pub attack: Duration,
+    /// Used while the level falls.
+    pub release: Duration,
+}
+
+impl Default for Smoothing {
+    fn default() -> Self {
+        Self {
+            attack: Duration::from_millis(10),
+            release: Duration::from_millis(200),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AutoGainSettings {
+    /// How long the rolling maximum remembers a peak.
+    pub window: Duration,
+    /// Peaks below this are never amplified further, capping the gain at `1.0 / min_peak`.
+    pub min_peak: f32,
+    /// Levels below this, before any gain, map to 0.0 so that the noise floor isn't amplified.
+    pub gate: f32,
+}
+
+impl Default for AutoGainSettings {
+    fn default() -> Self {
+        Self {
+            window: Duration::from_secs(10),
+            min_peak: 0.1,
+            gate: 0.1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LevelSettings {
+    pub smoothing: Smoothing,
+    /// Per-band overrides of `smoothing`, from the lowest band up.
+    ///
+    /// Bands past the end of the list use `smoothing`.
+    pub band_smoothing: Vec<Smoothing>,
+    /// Magnitudes at or below this level (in dBFS) map to 0.0.
+    pub floor_db: f32,
+    /// Magnitudes at or above this level (in dBFS) map to 1.0.
+    pub ceiling_db: f32,
+    pub auto_gain: Option<AutoGainSettings>,
+}
+
+impl Default for LevelSettings {
+    fn default() -> Self {
+        Self {
+            smoothing: Smoothing::default(),
+            band_smoothing: Vec::new(),
+            floor_db: -60.0,
+            ceiling_db: 0.0,
+            auto_gain: Some(AutoGainSettings::default()),
+        }
+    }
+}
+
+/// Turns raw band magnitudes into stable `0.0..=1.0` levels.
+///
+/// Magnitudes go through dB conversion, auto-gain and attack/release smoothing, in that order.
+#[derive(Debug, Clone)]
+pub struct LevelProcessor {
+    settings: LevelSettings,
+    levels: Vec<f32>,
+    elapsed: Duration,
+    // decreasing peaks of the auto-gain window, with the time they were seen
+    peaks: VecDeque<(Duration, f32)>,
+    gain: f32,
+}
+
+impl LevelProcessor {
+    pub fn new(settings: LevelSettings, bands: usize) -> Self {
+        assert!(
+            settings.floor_db < settings.ceiling_db,
+            "floor must be below ceiling"
+        );
+
+        Self {
+            settings,
+            levels: vec![0.0; bands],
+            elapsed: Duration::from_secs(0),
+            peaks: VecDeque::new(),
+            gain: 1.0,
+        }
+    }
+
+    pub fn settings(&self) -> &LevelSettings {
+        &self.settings
+    }
+
+    /// Advances time by `delta` and feeds one magnitude per band.
+    pub fn process(&mut self, delta: Duration, magnitudes: &[f32]) -> &[f32] {
+        self.elapsed += delta;
+
+        let range = self.settings.ceiling_db - self.settings.floor_db;
+        let mut targets: Vec<f32> = magnitudes
+            .iter()
+            .take(self.levels.len())
+            .map(|&m| {
+                let db = 20.0 * m.max(f32::MIN_POSITIVE).log10();
+
+                ((db - self.settings.floor_db) / range).clamp(0.0, 1.0)
+            })
+            .collect();
+
+        if let Some(auto_gain) = &self.settings.auto_gain {
+            for target in targets.iter_mut().filter(|t| **t < auto_gain.gate) {
+                *target = 0.0;
+            }
+
+            let peak = targets.iter().cloned().fold(0.0, f32::max);
+
+            while self.peaks.back().map_or(false, |&(_, p)| p <= peak) {
+                self.peaks.pop_back();
+            }
+            self.peaks.push_back((self.elapsed, peak));
+
+            while self
+                .peaks
+                .front()
+                .map_or(false, |&(t, _)| t + auto_gain.window < self.elapsed)
+            {
+                self.peaks.pop_front();
+            }
+
+            let max = self.peaks.front().map_or(0.0, |&(_, p)| p);
+            self.gain = 1.0 / max.max(auto_gain.min_peak);
+
+            for target in targets.iter_mut() {
+                *target = (*target * self.gain).min(1.0);
+            }
+        }
+
+        for (band, (level, target)) in self.levels.iter_mut().zip(targets).enumerate() {
+            let smoothing = self
+                .settings
+                .band_smoothing
+                .get(band)
+                .unwrap_or(&self.settings.smoothing);
+            let tau = if target > *level {
+                smoothing.attack
+            } else {
+                smoothing.release
+            };
+
+            *level += (target - *level) * coefficient(delta, tau);
+        }
+
+        &self.levels
+    }
+
+    pub fn levels(&self) -> &[f32] {
+        &self.levels
+    }
+
+    /// Gain applied by the auto-gain stage during the last call to `process`.
+    pub fn gain(&self) -> f32 {
+        self.gain
+    }
+
+    pub fn reset(&mut self) {
+        self.levels.iter_mut().for_each(|l| *l = 0.0);
+        self.elapsed = Duration::from_secs(0);
+        self.peaks.clear();
+        self.gain = 1.0;
+    }
+}
+
+fn coefficient(delta: Duration, tau: Duration) -> f32 {
+    if tau.as_nanos() == 0 {
+        1.0
+    } else {
+        1.0 - (-delta.as_secs_f32() / tau.as_secs_f32()).exp()
+    }
+}
@@ -0,0 +1,19 @@
+pub mod analyzer;
+pub mod bands;
+pub mod error;
+pub mod levels;
+pub mod player;
+pub mod source;
+
+pub mod prelude {
+    pub use crate::{
+        analyzer::{AnalyzerSettings, SpectrumAnalyzer, WindowFunction},
+        bands::{Aggregation, BandSettings, FrequencyScale},
+        error::Error,
+        levels::{AutoGainSettings, LevelProcessor, LevelSettings, Smoothing},
+        player::Player,
+        source::{AudioBuffer, AudioSource},
+    };
+}
+
+pub use crate::prelude::*;
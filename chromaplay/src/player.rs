@@ -0,0 +1,328 @@
+use crate::{AudioBuffer, Error};
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+const NULL_OUTPUT_PERIOD: Duration = Duration::from_millis(10);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlaybackState {
+    Playing,
+    Paused,
+    Stopped,
+    Finished,
+}
+
+/// Plays an [`AudioBuffer`] and keeps track of the playback position.
+pub struct Player {
+    shared: Arc<Shared>,
+    _output: Output,
+}
+
+impl Player {
+    /// Opens the default output device of the default host.
+    pub fn new(buffer: AudioBuffer) -> Result<Self, Error> {
+        let host = cpal::default_host();
+        let device = host.default_output_device().ok_or(Error::NoOutputDevice)?;
+        let config = output_config(&device, &buffer)?;
+        let sample_format = config.sample_format();
+        let config = config.into();
+        let shared = Arc::new(Shared::new(buffer));
+
+        let stream = match sample_format {
+            cpal::SampleFormat::F32 => build_stream::<f32>(&device, &config, shared.clone()),
+            cpal::SampleFormat::I16 => build_stream::<i16>(&device, &config, shared.clone()),
+            cpal::SampleFormat::U16 => build_stream::<u16>(&device, &config, shared.clone()),
+        }?;
+
+        stream.play()?;
+
+        Ok(Self {
+            shared,
+            _output: Output::Device(stream),
+        })
+    }
+
+    /// Consumes the audio in real time without sending it anywhere.
+    ///
+    /// Useful when there is no sound card, e.g. on CI.
+    pub fn with_null_output(buffer: AudioBuffer) -> Self {
+        let shared = Arc::new(Shared::new(buffer));
+
+        Self {
+            _output: Output::Null(NullOutput::spawn(shared.clone())),
+            shared,
+        }
+    }
+
+    pub fn buffer(&self) -> &AudioBuffer {
+        &self.shared.buffer
+    }
+
+    pub fn duration(&self) -> Duration {
+        self.shared.buffer.duration()
+    }
+
+    pub fn state(&self) -> PlaybackState {
+        self.shared.state.lock().unwrap().playback
+    }
+
+    pub fn play(&self) {
+        let mut state = self.shared.state.lock().unwrap();
+
+        match state.playback {
+            PlaybackState::Playing => {}
+            PlaybackState::Finished => {
+                state.seek(0.0);
+                state.playback = PlaybackState::Playing;
+            }
+            _ => state.playback = PlaybackState::Playing,
+        }
+    }
+
+    pub fn pause(&self) {
+        let mut state = self.shared.state.lock().unwrap();
+
+        if state.playback == PlaybackState::Playing {
+            // resume from what was actually heard, not from what was buffered
+            let position = state.position(self.shared.buffer.sample_rate());
+
+            state.seek(position);
+            state.playback = PlaybackState::Paused;
+        }
+    }
+
+    pub fn stop(&self) {
+        let mut state = self.shared.state.lock().unwrap();
+
+        state.seek(0.0);
+        state.playback = PlaybackState::Stopped;
+    }
+
+    pub fn seek(&self, time: Duration) {
+        let frame = self.shared.buffer.frame_at(time);
+        let mut state = self.shared.state.lock().unwrap();
+
+        state.seek(frame as f64);
+
+        if state.playback == PlaybackState::Finished {
+            state.playback = PlaybackState::Paused;
+        }
+    }
+
+    /// Current playback position.
+    ///
+    /// It never goes backwards unless the player is seeked or stopped.
+    pub fn position(&self) -> Duration {
+        let rate = self.shared.buffer.sample_rate();
+        let mut state = self.shared.state.lock().unwrap();
+        let frame = state.position(rate).max(state.reported);
+
+        state.reported = frame;
+
+        Duration::from_secs_f64(frame / rate as f64)
+    }
+}
+
+struct State {
+    playback: PlaybackState,
+    // source frame the next output frame will be read from (fractional when resampling)
+    cursor: f64,
+    // cursor at the start of the last block handed to the output, and when it was handed
+    block_start: f64,
+    block_time: Option<Instant>,
+    // last value returned by `Player::position`
+    reported: f64,
+}
+
+impl State {
+    fn seek(&mut self, frame: f64) {
+        self.cursor = frame;
+        self.block_start = frame;
+        self.block_time = None;
+        self.reported = frame;
+    }
+
+    // the output consumes a block faster than real time, so interpolate inside of it
+    fn position(&self, rate: u32) -> f64 {
+        match (self.playback, self.block_time) {
+            (PlaybackState::Playing, Some(time)) | (PlaybackState::Finished, Some(time)) => {
+                let elapsed = time.elapsed().as_secs_f64() * rate as f64;
+
+                (self.block_start + elapsed).min(self.cursor)
+            }
+            _ => self.cursor,
+        }
+    }
+}
+
+struct Shared {
+    buffer: AudioBuffer,
+    state: Mutex<State>,
+}
+
+impl Shared {
+    fn new(buffer: AudioBuffer) -> Self {
+        Self {
+            buffer,
+            state: Mutex::new(State {
+                playback: PlaybackState::Stopped,
+                cursor: 0.0,
+                block_start: 0.0,
+                block_time: None,
+                reported: 0.0,
+            }),
+        }
+    }
+
+    fn fill<T: cpal::Sample>(&self, out: &mut [T], channels: usize, rate: u32) {
+        let silence = T::from(&0.0f32);
+        let mut state = self.state.lock().unwrap();
+
+        if state.playback != PlaybackState::Playing {
+            out.iter_mut().for_each(|s| *s = silence);
+            return;
+        }
+
+        let frames = self.buffer.frames();
+        let step = self.buffer.sample_rate() as f64 / rate as f64;
+
+        state.block_start = state.cursor;
+        state.block_time = Some(Instant::now());
+
+        for frame in out.chunks_mut(channels) {
+            if state.cursor >= frames as f64 {
+                state.playback = PlaybackState::Finished;
+                state.cursor = frames as f64;
+                frame.iter_mut().for_each(|s| *s = silence);
+                continue;
+            }
+
+            let index = state.cursor as usize;
+            let t = (state.cursor - index as f64) as f32;
+            let a = self.buffer.frame(index);
+            let b = self.buffer.frame((index + 1).min(frames - 1));
+
+            for (c, s) in frame.iter_mut().enumerate() {
+                let a = map_channel(a, c, channels);
+                let b = map_channel(b, c, channels);
+
+                *s = T::from(&(a + (b - a) * t));
+            }
+
+            state.cursor += step;
+        }
+    }
+}
+
+fn map_channel(frame: &[f32], channel: usize, channels: usize) -> f32 {
+    if channels == 1 {
+        frame.iter().sum::<f32>() / frame.len() as f32
+    } else if frame.len() == 1 {
+        frame[0]
+    } else {
+        frame.get(channel).copied().unwrap_or(0.0)
+    }
+}
+
+// only held to keep the output running for as long as the player lives
+#[allow(dead_code)]
+enum Output {
+    Device(cpal::Stream),
+    Null(NullOutput),
+}
+
+struct NullOutput {
+    running: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl NullOutput {
+    fn spawn(shared: Arc<Shared>) -> Self {
+        let running = Arc::new(AtomicBool::new(true));
+
+        let thread = thread::spawn({
+            let running = running.clone();
+
+            move || {
+                let rate = shared.buffer.sample_rate();
+                let channels = shared.buffer.channels() as usize;
+                let start = Instant::now();
+                let mut written = 0;
+                let mut block = Vec::new();
+
+                while running.load(Ordering::Relaxed) {
+                    // stay one period ahead, like a real device would
+                    let due = ((start.elapsed() + NULL_OUTPUT_PERIOD).as_secs_f64() * rate as f64)
+                        as usize;
+
+                    if due > written {
+                        block.resize((due - written) * channels, 0.0f32);
+                        shared.fill(&mut block, channels, rate);
+                        written = due;
+                    }
+
+                    thread::sleep(NULL_OUTPUT_PERIOD);
+                }
+            }
+        });
+
+        Self {
+            running,
+            thread: Some(thread),
+        }
+    }
+}
+
+impl Drop for NullOutput {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::Relaxed);
+
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+// prefer a config that plays the buffer as-is, resample otherwise
+fn output_config(
+    device: &cpal::Device,
+    buffer: &AudioBuffer,
+) -> Result<cpal::SupportedStreamConfig, Error> {
+    let rate = cpal::SampleRate(buffer.sample_rate());
+    let matching = device
+        .supported_output_configs()?
+        .filter(|c| c.min_sample_rate() <= rate && rate <= c.max_sample_rate())
+        .max_by_key(|c| {
+            (
+                c.channels() == buffer.channels(),
+                c.sample_format() == cpal::SampleFormat::F32,
+            )
+        });
+
+    Ok(match matching {
+        Some(config) => config.with_sample_rate(rate),
+        None => device.default_output_config()?,
+    })
+}
+
+fn build_stream<T: cpal::Sample>(
+    device: &cpal::Device,
+    config: &cpal::StreamConfig,
+    shared: Arc<Shared>,
+) -> Result<cpal::Stream, Error> {
+    let channels = config.channels as usize;
+    let rate = config.sample_rate.0;
+
+    Ok(device.build_output_stream(
+        config,
+        move |data: &mut [T], _: &cpal::OutputCallbackInfo| shared.fill(data, channels, rate),
+        |e| eprintln!("audio output error: {}", e),
+    )?)
+}
@@ -0,0 +1,215 @@
+use crate::Error;
+use hound::{SampleFormat, WavReader};
+use std::{
+    fs::File,
+    io::{BufReader, Read, Seek},
+    path::Path,
+    sync::Arc,
+    time::Duration,
+};
+
+/// Streaming WAV decoder yielding interleaved samples normalized to `-1.0..=1.0`.
+pub struct AudioSource<R> {
+    reader: WavReader<R>,
+    sample_rate: u32,
+    channels: u16,
+    format: SampleFormat,
+    scale: f32,
+    position: u32,
+}
+
+impl AudioSource<BufReader<File>> {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        Self::new(BufReader::new(File::open(path)?))
+    }
+}
+
+impl<R: Read + Seek> AudioSource<R> {
+    pub fn new(reader: R) -> Result<Self, Error> {
+        let reader = WavReader::new(reader)?;
+        let spec = reader.spec();
+
+        let scale = match spec.sample_format {
+            SampleFormat::Float => 1.0,
+            SampleFormat::Int => 1.0 / (1u64 << (spec.bits_per_sample - 1)) as f32,
+        };
+
+        Ok(Self {
+            reader,
+            sample_rate: spec.sample_rate,
+            channels: spec.channels,
+            format: spec.sample_format,
+            scale,
+            position: 0,
+        })
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    pub fn channels(&self) -> u16 {
+        self.channels
+    }
+
+    /// Total length of the stream, in frames.
+    pub fn frames(&self) -> u32 {
+        self.reader.duration()
+    }
+
+    pub fn duration(&self) -> Duration {
+        Duration::from_secs_f64(self.frames() as f64 / self.sample_rate as f64)
+    }
+
+    /// Index of the next frame that will be read.
+    pub fn position(&self) -> u32 {
+        self.position
+    }
+
+    pub fn seek(&mut self, frame: u32) -> Result<(), Error> {
+        let frame = frame.min(self.frames());
+
+        self.reader.seek(frame)?;
+        self.position = frame;
+
+        Ok(())
+    }
+
+    /// Reads as many whole frames as fit in `buf` and returns how many were read.
+    ///
+    /// Returns `0` once the end of the stream has been reached.
+    pub fn read_frames(&mut self, buf: &mut [f32]) -> Result<usize, Error> {
+        let channels = self.channels as usize;
+        let wanted = buf.len() / channels * channels;
+        let mut read = 0;
+
+        match self.format {
+            SampleFormat::Float => {
+                for (dst, sample) in buf[..wanted].iter_mut().zip(self.reader.samples::<f32>()) {
+                    *dst = sample?;
+                    read += 1;
+                }
+            }
+            SampleFormat::Int => {
+                let scale = self.scale;
+
+                for (dst, sample) in buf[..wanted].iter_mut().zip(self.reader.samples::<i32>()) {
+                    *dst = sample? as f32 * scale;
+                    read += 1;
+                }
+            }
+        }
+
+        let frames = read / channels;
+        self.position += frames as u32;
+
+        Ok(frames)
+    }
+
+    /// Iterates over the rest of the stream in chunks of `frames_per_chunk` frames.
+    ///
+    /// The last chunk may be shorter.
+    pub fn chunks(&mut self, frames_per_chunk: usize) -> Chunks<'_, R> {
+        Chunks {
+            len: frames_per_chunk * self.channels as usize,
+            source: self,
+        }
+    }
+
+    /// Decodes the rest of the stream into memory.
+    pub fn into_buffer(mut self) -> Result<AudioBuffer, Error> {
+        let remaining = (self.frames() - self.position) as usize * self.channels as usize;
+        let mut samples = vec![0.0; remaining];
+        let frames = self.read_frames(&mut samples)?;
+
+        samples.truncate(frames * self.channels as usize);
+
+        Ok(AudioBuffer::new(self.sample_rate, self.channels, samples))
+    }
+}
+
+pub struct Chunks<'a, R> {
+    source: &'a mut AudioSource<R>,
+    len: usize,
+}
+
+impl<'a, R: Read + Seek> Iterator for Chunks<'a, R> {
+    type Item = Result<Vec<f32>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut chunk = vec![0.0; self.len];
+
+        match self.source.read_frames(&mut chunk) {
+            Ok(0) => None,
+            Ok(frames) => {
+                chunk.truncate(frames * self.source.channels as usize);
+                Some(Ok(chunk))
+            }
+            Err(e) => Some(Err(e)),
+        }
+    }
+}
+
+/// Fully decoded audio, cheap to clone and share between threads.
+#[derive(Debug, Clone)]
+pub struct AudioBuffer {
+    sample_rate: u32,
+    channels: u16,
+    samples: Arc<[f32]>,
+}
+
+impl AudioBuffer {
+    /// Wraps interleaved samples. Any trailing partial frame is discarded.
+    pub fn new(sample_rate: u32, channels: u16, mut samples: Vec<f32>) -> Self {
+        assert!(sample_rate > 0, "sample rate must be positive");
+        assert!(channels > 0, "there must be at least one channel");
+
+        samples.truncate(samples.len() / channels as usize * channels as usize);
+
+        Self {
+            sample_rate,
+            channels,
+            samples: samples.into(),
+        }
+    }
+
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        AudioSource::open(path)?.into_buffer()
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    pub fn channels(&self) -> u16 {
+        self.channels
+    }
+
+    pub fn frames(&self) -> usize {
+        self.samples.len() / self.channels as usize
+    }
+
+    pub fn duration(&self) -> Duration {
+        Duration::from_secs_f64(self.frames() as f64 / self.sample_rate as f64)
+    }
+
+    /// Interleaved samples.
+    pub fn samples(&self) -> &[f32] {
+        &self.samples
+    }
+
+    pub fn frame(&self, index: usize) -> &[f32] {
+        let channels = self.channels as usize;
+
+        &self.samples[index * channels..(index + 1) * channels]
+    }
+
+    /// Index of the frame playing at `time`, clamped to the end of the buffer.
+    pub fn frame_at(&self, time: Duration) -> usize {
+        ((time.as_secs_f64() * self.sample_rate as f64) as usize).min(self.frames())
+    }
+
+    pub fn time_at(&self, frame: usize) -> Duration {
+        Duration::from_secs_f64(frame as f64 / self.sample_rate as f64)
+    }
+}
@@ -0,0 +1,95 @@
+use chromaplay::prelude::*;
+use std::f32::consts::PI;
+
+const RATE: u32 = 8192;
+
+fn sine(frequency: f32, amplitude: f32, frames: usize) -> AudioBuffer {
+    let samples = (0..frames)
+        .map(|i| (2.0 * PI * frequency * i as f32 / RATE as f32).sin() * amplitude)
+        .collect();
+
+    AudioBuffer::new(RATE, 1, samples)
+}
+
+fn analyzer(window: WindowFunction) -> SpectrumAnalyzer {
+    SpectrumAnalyzer::new(AnalyzerSettings {
+        fft_size: 1024,
+        hop: 256,
+        window,
+        bands: 16,
+        mapping: BandSettings {
+            scale: FrequencyScale::Linear,
+            min_frequency: 0.0,
+            max_frequency: RATE as f32 / 2.0,
+            aggregation: Aggregation::Peak,
+        },
+    })
+}
+
+#[test]
+fn full_scale_sine_peaks_at_one() {
+    for &window in &[
+        WindowFunction::Rectangular,
+        WindowFunction::Hann,
+        WindowFunction::Hamming,
+        WindowFunction::Blackman,
+    ] {
+        let mut analyzer = analyzer(window);
+        // exactly on bin 64
+        let buffer = sine(512.0, 1.0, 4096);
+
+        analyzer.analyze(&buffer, 4096);
+
+        let peak = analyzer.magnitudes()[64];
+        assert!((peak - 1.0).abs() < 0.01, "{:?}: {}", window, peak);
+    }
+}
+
+#[test]
+fn bands_follow_frequency() {
+    let mut analyzer = analyzer(WindowFunction::Hann);
+
+    // 256 Hz per band
+    let bands = analyzer.analyze(&sine(2000.0, 0.5, 4096), 4096).to_vec();
+    let loudest = (0..bands.len())
+        .max_by(|&a, &b| bands[a].partial_cmp(&bands[b]).unwrap())
+        .unwrap();
+
+    assert_eq!(bands.len(), 16);
+    assert_eq!(loudest, 7);
+    assert!((bands[7] - 0.5).abs() < 0.05, "{}", bands[7]);
+    assert!(bands[0] < 0.01 && bands[15] < 0.01);
+}
+
+#[test]
+fn silence_is_zero() {
+    let mut analyzer = analyzer(WindowFunction::Hann);
+    let buffer = AudioBuffer::new(RATE, 2, vec![0.0; 8192]);
+
+    assert!(analyzer.analyze(&buffer, 2000).iter().all(|&b| b == 0.0));
+}
+
+#[test]
+fn snaps_to_hop() {
+    let mut analyzer = analyzer(WindowFunction::Hann);
+    let buffer = sine(700.0, 0.8, 8192);
+
+    let a = analyzer.analyze(&buffer, 2048).to_vec();
+    let b = analyzer.analyze(&buffer, 2048 + 255).to_vec();
+    let c = analyzer.analyze(&buffer, 2048 + 256).to_vec();
+
+    assert_eq!(a, b);
+    assert_ne!(a, c);
+}
+
+#[test]
+fn pads_before_start() {
+    let mut analyzer = analyzer(WindowFunction::Rectangular);
+    let buffer = sine(512.0, 1.0, 4096);
+
+    // only half of the window overlaps the buffer
+    analyzer.analyze(&buffer, 512);
+
+    let peak = analyzer.magnitudes()[64];
+    assert!((peak - 0.5).abs() < 0.01, "{}", peak);
+}
@@ -0,0 +1,145 @@
+use chromaplay::{bands::BandMapper, prelude::*};
+
+fn settings(scale: FrequencyScale, aggregation: Aggregation) -> BandSettings {
+    BandSettings {
+        scale,
+        min_frequency: 20.0,
+        max_frequency: 20000.0,
+        aggregation,
+    }
+}
+
+#[test]
+fn edges_span_the_frequency_range() {
+    for &scale in &[
+        FrequencyScale::Linear,
+        FrequencyScale::Logarithmic,
+        FrequencyScale::Mel,
+        FrequencyScale::Bark,
+    ] {
+        let mapper = BandMapper::new(settings(scale, Aggregation::Peak), 32);
+        let edges = mapper.edges();
+
+        assert_eq!(edges.len(), 33);
+        assert!((edges[0] - 20.0).abs() < 0.01, "{:?}: {}", scale, edges[0]);
+        assert!(
+            (edges[32] - 20000.0).abs() < 1.0,
+            "{:?}: {}",
+            scale,
+            edges[32]
+        );
+        assert!(edges.windows(2).all(|e| e[0] < e[1]), "{:?}", scale);
+    }
+}
+
+#[test]
+fn perceptual_scales_favor_low_frequencies() {
+    let below_1k = |scale| {
+        BandMapper::new(settings(scale, Aggregation::Peak), 32)
+            .edges()
+            .iter()
+            .filter(|&&f| f < 1000.0)
+            .count()
+    };
+
+    let linear = below_1k(FrequencyScale::Linear);
+    let mel = below_1k(FrequencyScale::Mel);
+    let bark = below_1k(FrequencyScale::Bark);
+    let log = below_1k(FrequencyScale::Logarithmic);
+
+    assert!(linear < mel, "{} {}", linear, mel);
+    assert!(linear < bark, "{} {}", linear, bark);
+    assert!(mel < log, "{} {}", mel, log);
+}
+
+#[test]
+fn octave_fractions_ignore_max_frequency() {
+    let mapper = BandMapper::new(
+        settings(FrequencyScale::OctaveFraction(3), Aggregation::Peak),
+        30,
+    );
+    let edges = mapper.edges();
+
+    // 30 third-octaves are 10 octaves
+    assert!((edges[30] - 20480.0).abs() < 1.0, "{}", edges[30]);
+
+    for e in edges.windows(2) {
+        assert!((e[1] / e[0] - 2f32.powf(1.0 / 3.0)).abs() < 1e-4);
+    }
+}
+
+#[test]
+fn aggregates_bins() {
+    // 4 bins of 1 Hz, one band covering bins 1..4
+    let magnitudes = [0.0, 0.2, 0.4, 0.6, 0.0];
+    let map = |aggregation| {
+        let mut mapper = BandMapper::new(
+            BandSettings {
+                scale: FrequencyScale::Linear,
+                min_frequency: 0.5,
+                max_frequency: 3.5,
+                aggregation,
+            },
+            1,
+        );
+        let mut out = [0.0];
+
+        mapper.map(&magnitudes, 8, &mut out);
+        out[0]
+    };
+
+    assert!((map(Aggregation::Peak) - 0.6).abs() < 1e-6);
+    assert!((map(Aggregation::Mean) - 0.4).abs() < 1e-6);
+    assert!((map(Aggregation::Rms) - (0.56f32 / 3.0).sqrt()).abs() < 1e-6);
+}
+
+#[test]
+fn narrow_bands_interpolate_between_bins() {
+    // 100 Hz bins, bands much narrower than that
+    let magnitudes = [0.0, 1.0, 0.0, 0.0, 0.0];
+    let mut mapper = BandMapper::new(
+        BandSettings {
+            scale: FrequencyScale::Linear,
+            min_frequency: 100.0,
+            max_frequency: 200.0,
+            aggregation: Aggregation::Peak,
+        },
+        4,
+    );
+    let mut out = [0.0; 4];
+
+    mapper.map(&magnitudes, 800, &mut out);
+
+    // bin 1 (100 Hz) lands in the first band, the others sit between bins 1 and 2
+    assert_eq!(out[0], 1.0);
+    assert!(
+        out[1] > out[2] && out[2] > out[3] && out[3] > 0.0,
+        "{:?}",
+        out
+    );
+}
+
+#[test]
+#[should_panic(expected = "positive minimum frequency")]
+fn logarithmic_scales_reject_zero_hz() {
+    BandMapper::new(
+        BandSettings {
+            min_frequency: 0.0,
+            ..settings(FrequencyScale::OctaveFraction(3), Aggregation::Peak)
+        },
+        30,
+    );
+}
+
+#[test]
+fn linear_scales_start_at_zero_hz() {
+    let mapper = BandMapper::new(
+        BandSettings {
+            min_frequency: 0.0,
+            ..settings(FrequencyScale::Mel, Aggregation::Peak)
+        },
+        8,
+    );
+
+    assert_eq!(mapper.edges()[0], 0.0);
+}
@@ -0,0 +1,162 @@
+use chromaplay::prelude::*;
+use std::time::Duration;
+
+const FRAME: Duration = Duration::from_millis(10);
+
+fn instant() -> Smoothing {
+    Smoothing {
+        attack: Duration::from_secs(0),
+        release: Duration::from_secs(0),
+    }
+}
+
+fn settings(smoothing: Smoothing, auto_gain: Option<AutoGainSettings>) -> LevelSettings {
+    LevelSettings {
+        smoothing,
+        band_smoothing: Vec::new(),
+        floor_db: -60.0,
+        ceiling_db: 0.0,
+        auto_gain,
+    }
+}
+
+fn db(db: f32) -> f32 {
+    10f32.powf(db / 20.0)
+}
+
+#[test]
+fn maps_decibels_between_floor_and_ceiling() {
+    let mut levels = LevelProcessor::new(settings(instant(), None), 4);
+    let out = levels.process(FRAME, &[db(0.0), db(-30.0), db(-60.0), 0.0]);
+
+    assert!((out[0] - 1.0).abs() < 1e-5);
+    assert!((out[1] - 0.5).abs() < 1e-5);
+    assert!(out[2].abs() < 1e-5);
+    assert_eq!(out[3], 0.0);
+}
+
+#[test]
+fn attack_and_release_follow_time_constants() {
+    let smoothing = Smoothing {
+        attack: Duration::from_millis(50),
+        release: Duration::from_millis(500),
+    };
+    let mut levels = LevelProcessor::new(settings(smoothing, None), 1);
+
+    // 5 frames of 10ms = one attack time constant
+    for _ in 0..5 {
+        levels.process(FRAME, &[1.0]);
+    }
+    assert!(
+        (levels.levels()[0] - 0.632).abs() < 0.01,
+        "{}",
+        levels.levels()[0]
+    );
+
+    for _ in 0..100 {
+        levels.process(FRAME, &[1.0]);
+    }
+
+    // one release time constant
+    for _ in 0..50 {
+        levels.process(FRAME, &[0.0]);
+    }
+    assert!(
+        (levels.levels()[0] - 0.368).abs() < 0.01,
+        "{}",
+        levels.levels()[0]
+    );
+}
+
+#[test]
+fn bands_can_override_smoothing() {
+    let mut settings = settings(instant(), None);
+    settings.band_smoothing = vec![Smoothing {
+        attack: Duration::from_secs(1),
+        release: Duration::from_secs(1),
+    }];
+
+    let mut levels = LevelProcessor::new(settings, 2);
+    let out = levels.process(FRAME, &[1.0, 1.0]);
+
+    assert!(out[0] < 0.1);
+    assert_eq!(out[1], 1.0);
+}
+
+#[test]
+fn auto_gain_fills_the_range() {
+    let mut levels = LevelProcessor::new(settings(instant(), Some(AutoGainSettings::default())), 2);
+
+    // a quiet track peaking at -30 dB
+    let out = levels.process(FRAME, &[db(-30.0), db(-45.0)]);
+
+    assert!((out[0] - 1.0).abs() < 1e-5);
+    assert!((out[1] - 0.5).abs() < 1e-5);
+    assert!((levels.gain() - 2.0).abs() < 1e-5);
+}
+
+#[test]
+fn auto_gain_forgets_old_peaks() {
+    let auto_gain = AutoGainSettings {
+        window: Duration::from_secs(1),
+        min_peak: 0.1,
+        gate: 0.1,
+    };
+    let mut levels = LevelProcessor::new(settings(instant(), Some(auto_gain)), 1);
+
+    levels.process(FRAME, &[1.0]);
+
+    // still within the window: the loud peak holds the gain down
+    for _ in 0..50 {
+        levels.process(FRAME, &[db(-30.0)]);
+    }
+    assert!((levels.levels()[0] - 0.5).abs() < 1e-5);
+
+    for _ in 0..60 {
+        levels.process(FRAME, &[db(-30.0)]);
+    }
+    assert!((levels.levels()[0] - 1.0).abs() < 1e-5);
+}
+
+#[test]
+fn auto_gain_caps_the_gain() {
+    let auto_gain = AutoGainSettings {
+        gate: 0.0,
+        ..Default::default()
+    };
+    let mut levels = LevelProcessor::new(settings(instant(), Some(auto_gain)), 1);
+
+    // -57 dB is 0.05 after dB mapping, below `min_peak`
+    let out = levels.process(FRAME, &[db(-57.0)]);
+
+    assert!((out[0] - 0.5).abs() < 1e-4, "{}", out[0]);
+    assert!((levels.gain() - 10.0).abs() < 1e-4);
+}
+
+#[test]
+fn auto_gain_gates_the_noise_floor() {
+    let mut levels = LevelProcessor::new(settings(instant(), Some(AutoGainSettings::default())), 2);
+
+    // hiss at -57 dB stays silent, while -50 dB is loud enough to be boosted
+    let out = levels.process(FRAME, &[db(-57.0), db(-50.0)]);
+
+    assert_eq!(out[0], 0.0);
+    assert!((out[1] - 1.0).abs() < 1e-4, "{}", out[1]);
+}
+
+#[test]
+fn is_deterministic() {
+    let signal: Vec<f32> = (0..500)
+        .map(|i| ((i as f32 * 0.37).sin() + 1.0) / 2.0)
+        .collect();
+    let run = || {
+        let mut levels = LevelProcessor::new(LevelSettings::default(), 1);
+
+        signal
+            .iter()
+            .map(|&s| levels.process(FRAME, &[s])[0])
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(run(), run());
+}
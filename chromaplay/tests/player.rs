@@ -0,0 +1,90 @@
+use chromaplay::{player::PlaybackState, prelude::*};
+use std::{thread, time::Duration};
+
+fn silence(duration: Duration) -> AudioBuffer {
+    let frames = (duration.as_secs_f64() * 8000.0) as usize;
+
+    AudioBuffer::new(8000, 2, vec![0.0; frames * 2])
+}
+
+#[test]
+fn starts_stopped() {
+    let player = Player::with_null_output(silence(Duration::from_secs(1)));
+
+    thread::sleep(Duration::from_millis(50));
+
+    assert_eq!(player.state(), PlaybackState::Stopped);
+    assert_eq!(player.position(), Duration::from_secs(0));
+}
+
+#[test]
+fn position_follows_the_clock() {
+    let player = Player::with_null_output(silence(Duration::from_secs(10)));
+
+    player.play();
+    thread::sleep(Duration::from_millis(300));
+
+    let position = player.position();
+    assert!(position > Duration::from_millis(150), "{:?}", position);
+    assert!(position < Duration::from_millis(600), "{:?}", position);
+}
+
+#[test]
+fn position_is_monotonic() {
+    let player = Player::with_null_output(silence(Duration::from_secs(10)));
+    let mut last = Duration::from_secs(0);
+
+    player.play();
+
+    for _ in 0..200 {
+        let position = player.position();
+        assert!(position >= last, "{:?} < {:?}", position, last);
+        last = position;
+        thread::sleep(Duration::from_millis(1));
+    }
+
+    assert!(last > Duration::from_secs(0));
+}
+
+#[test]
+fn pause_freezes_position() {
+    let player = Player::with_null_output(silence(Duration::from_secs(10)));
+
+    player.play();
+    thread::sleep(Duration::from_millis(100));
+    player.pause();
+
+    let paused_at = player.position();
+    thread::sleep(Duration::from_millis(100));
+
+    assert_eq!(player.state(), PlaybackState::Paused);
+    assert_eq!(player.position(), paused_at);
+}
+
+#[test]
+fn seek_and_stop_move_position() {
+    let player = Player::with_null_output(silence(Duration::from_secs(10)));
+
+    player.seek(Duration::from_secs(4));
+    assert_eq!(player.position(), Duration::from_secs(4));
+
+    player.play();
+    thread::sleep(Duration::from_millis(50));
+    player.seek(Duration::from_secs(2));
+    assert!(player.position() < Duration::from_secs(3));
+
+    player.stop();
+    assert_eq!(player.state(), PlaybackState::Stopped);
+    assert_eq!(player.position(), Duration::from_secs(0));
+}
+
+#[test]
+fn finishes_at_the_end() {
+    let player = Player::with_null_output(silence(Duration::from_millis(100)));
+
+    player.play();
+    thread::sleep(Duration::from_millis(400));
+
+    assert_eq!(player.state(), PlaybackState::Finished);
+    assert_eq!(player.position(), player.duration());
+}
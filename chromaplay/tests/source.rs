@@ -0,0 +1,146 @@
+use chromaplay::prelude::*;
+use hound::{SampleFormat, WavSpec, WavWriter};
+use std::{io::Cursor, time::Duration};
+
+fn spec(channels: u16, bits_per_sample: u16, sample_format: SampleFormat) -> WavSpec {
+    WavSpec {
+        channels,
+        sample_rate: 8000,
+        bits_per_sample,
+        sample_format,
+    }
+}
+
+/// Encodes normalized samples as a WAV file of the given format.
+fn wav(spec: WavSpec, samples: &[f32]) -> Cursor<Vec<u8>> {
+    let mut cursor = Cursor::new(Vec::new());
+
+    {
+        let mut writer = WavWriter::new(&mut cursor, spec).unwrap();
+        let max = ((1i64 << (spec.bits_per_sample - 1)) - 1) as f32;
+
+        for &sample in samples {
+            match (spec.sample_format, spec.bits_per_sample) {
+                (SampleFormat::Float, _) => writer.write_sample(sample).unwrap(),
+                (SampleFormat::Int, 8) => writer.write_sample((sample * max) as i8).unwrap(),
+                (SampleFormat::Int, 16) => writer.write_sample((sample * max) as i16).unwrap(),
+                (SampleFormat::Int, _) => writer.write_sample((sample * max) as i32).unwrap(),
+            }
+        }
+
+        writer.finalize().unwrap();
+    }
+
+    cursor.set_position(0);
+    cursor
+}
+
+fn ramp(len: usize) -> Vec<f32> {
+    (0..len)
+        .map(|i| i as f32 / (len - 1) as f32 * 2.0 - 1.0)
+        .collect()
+}
+
+#[test]
+fn decodes_every_sample_format() {
+    let expected = ramp(64);
+
+    for &(bits, format, tolerance) in &[
+        (8, SampleFormat::Int, 1.0 / 64.0),
+        (16, SampleFormat::Int, 1e-4),
+        (24, SampleFormat::Int, 1e-6),
+        (32, SampleFormat::Int, 1e-6),
+        (32, SampleFormat::Float, 0.0),
+    ] {
+        let source = AudioSource::new(wav(spec(1, bits, format), &expected)).unwrap();
+        let buffer = source.into_buffer().unwrap();
+
+        assert_eq!(buffer.samples().len(), expected.len());
+
+        for (a, b) in buffer.samples().iter().zip(&expected) {
+            assert!(
+                (a - b).abs() <= tolerance,
+                "{}-bit {:?}: {} != {}",
+                bits,
+                format,
+                a,
+                b
+            );
+        }
+    }
+}
+
+#[test]
+fn full_scale_int_stays_in_range() {
+    let mut cursor = Cursor::new(Vec::new());
+
+    {
+        let mut writer = WavWriter::new(&mut cursor, spec(1, 16, SampleFormat::Int)).unwrap();
+        writer.write_sample(i16::MIN).unwrap();
+        writer.write_sample(i16::MAX).unwrap();
+        writer.finalize().unwrap();
+    }
+
+    cursor.set_position(0);
+
+    let buffer = AudioSource::new(cursor).unwrap().into_buffer().unwrap();
+
+    assert_eq!(buffer.samples()[0], -1.0);
+    assert!(buffer.samples()[1] < 1.0 && buffer.samples()[1] > 0.999);
+}
+
+#[test]
+fn exposes_stream_metadata() {
+    let source = AudioSource::new(wav(spec(2, 16, SampleFormat::Int), &[0.0; 16000])).unwrap();
+
+    assert_eq!(source.sample_rate(), 8000);
+    assert_eq!(source.channels(), 2);
+    assert_eq!(source.frames(), 8000);
+    assert_eq!(source.duration(), Duration::from_secs(1));
+}
+
+#[test]
+fn reads_whole_frames_in_chunks() {
+    let samples = ramp(20);
+    let mut source = AudioSource::new(wav(spec(2, 32, SampleFormat::Float), &samples)).unwrap();
+
+    let chunks: Vec<Vec<f32>> = source.chunks(4).map(Result::unwrap).collect();
+
+    assert_eq!(
+        chunks.iter().map(Vec::len).collect::<Vec<_>>(),
+        vec![8, 8, 4]
+    );
+    assert_eq!(chunks.concat(), samples);
+    assert_eq!(source.position(), 10);
+}
+
+#[test]
+fn seeks_to_frame() {
+    let samples = ramp(20);
+    let mut source = AudioSource::new(wav(spec(2, 32, SampleFormat::Float), &samples)).unwrap();
+    let mut buf = [0.0; 4];
+
+    source.seek(7).unwrap();
+
+    assert_eq!(source.read_frames(&mut buf).unwrap(), 2);
+    assert_eq!(buf, samples[14..18]);
+    assert_eq!(source.read_frames(&mut buf).unwrap(), 1);
+    assert_eq!(source.read_frames(&mut buf).unwrap(), 0);
+}
+
+#[test]
+fn opens_files() {
+    let path = std::env::temp_dir().join(format!("chromaplay-{}.wav", std::process::id()));
+    std::fs::write(
+        &path,
+        wav(spec(1, 24, SampleFormat::Int), &ramp(10)).into_inner(),
+    )
+    .unwrap();
+
+    let buffer = AudioBuffer::open(&path);
+    std::fs::remove_file(&path).unwrap();
+
+    let buffer = buffer.unwrap();
+    assert_eq!(buffer.frames(), 10);
+    assert_eq!(buffer.frame_at(Duration::from_secs(1)), 10);
+}
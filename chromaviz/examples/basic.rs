@@ -36,19 +36,9 @@ fn run(event_loop: EventLoop<()>, window: Window) {
         size.width,
         size.height,
         wgpu::TextureFormat::Bgra8UnormSrgb,
-        ChromaSettings {
-            decay: 0.95,
-            particles: ParticleSettings {
-                gravity: (0.0, -4.0).into(),
-                frequencies: 32,
-                frequencies_spread: 1.0,
-                particles_per_second: 2000,
-                angular_spread: 2.0,
-                velocity_spread: 0.1,
-                size_range: 4.0..6.0,
-            },
-        },
-    );
+        ChromaSettings::default(),
+    )
+    .unwrap();
 
     let mut sc_desc = wgpu::SwapChainDescriptor {
         usage: wgpu::TextureUsage::OUTPUT_ATTACHMENT,
@@ -67,35 +57,33 @@ fn run(event_loop: EventLoop<()>, window: Window) {
         *control_flow = ControlFlow::Poll;
 
         match event {
-            Event::MainEventsCleared => {
-                if last_update_inst.elapsed() >= Duration::from_millis(16) {
-                    let t = start_inst.elapsed().as_secs_f32();
-                    let phase = t;
-                    let global_height = (t * 4.0).sin() * 0.2 + 0.4;
-                    let freq_data: Vec<f32> = (0..32)
-                        .map(|f| (0.5 * f as f32 + phase).sin() * 0.2 + global_height)
-                        .map(|f| f.max(0.0).min(1.0))
-                        .collect();
-                    renderer.update(last_update_inst.elapsed(), &freq_data);
-
-                    let frame = match swap_chain.get_current_frame() {
-                        Ok(frame) => frame,
-                        Err(_) => {
-                            swap_chain = device.create_swap_chain(&surface, &sc_desc);
-                            swap_chain
-                                .get_current_frame()
-                                .expect("Failed to get next swapchain texture!")
-                        }
-                    };
-
-                    let commands = renderer.render(&device, &frame.output.view);
-
-                    if !commands.is_empty() {
-                        queue.submit(commands);
+            Event::MainEventsCleared if last_update_inst.elapsed() >= Duration::from_millis(16) => {
+                let t = start_inst.elapsed().as_secs_f32();
+                let phase = t;
+                let global_height = (t * 4.0).sin() * 0.2 + 0.4;
+                let freq_data: Vec<f32> = (0..32)
+                    .map(|f| (0.5 * f as f32 + phase).sin() * 0.2 + global_height)
+                    .map(|f| f.clamp(0.0, 1.0))
+                    .collect();
+                renderer.update(last_update_inst.elapsed(), &freq_data);
+
+                let frame = match swap_chain.get_current_frame() {
+                    Ok(frame) => frame,
+                    Err(_) => {
+                        swap_chain = device.create_swap_chain(&surface, &sc_desc);
+                        swap_chain
+                            .get_current_frame()
+                            .expect("Failed to get next swapchain texture!")
                     }
+                };
+
+                let commands = renderer.render(&device, &frame.output.view);
 
-                    last_update_inst = Instant::now();
+                if !commands.is_empty() {
+                    queue.submit(commands);
                 }
+
+                last_update_inst = Instant::now();
             }
             Event::WindowEvent {
                 event: WindowEvent::Resized(size),
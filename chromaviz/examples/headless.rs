@@ -0,0 +1,74 @@
+use std::{env::args, time::Duration};
+
+use chromaviz::prelude::*;
+
+use futures::executor::block_on;
+
+const WIDTH: u32 = 640;
+const HEIGHT: u32 = 480;
+const FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8UnormSrgb;
+
+fn main() {
+    let dir = args().nth(1).unwrap_or_else(|| String::from("frames"));
+    let frame_count: usize = args().nth(2).and_then(|n| n.parse().ok()).unwrap_or(120);
+
+    let instance = wgpu::Instance::new(wgpu::BackendBit::PRIMARY);
+
+    let adapter = block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
+        power_preference: wgpu::PowerPreference::Default,
+        compatible_surface: None,
+    }))
+    .expect("no suitable adapter found");
+
+    let (device, queue) = block_on(adapter.request_device(
+        &wgpu::DeviceDescriptor {
+            features: wgpu::Features::empty(),
+            limits: wgpu::Limits::default(),
+            shader_validation: true,
+        },
+        None,
+    ))
+    .unwrap();
+
+    let target = OffscreenTarget::new(&device, WIDTH, HEIGHT, FORMAT);
+    let mut renderer = Chroma::new(
+        &device,
+        WIDTH,
+        HEIGHT,
+        FORMAT,
+        ChromaSettings {
+            emitters: vec![Emitter {
+                particles: ParticleSettings {
+                    seed: Some(0),
+                    ..Default::default()
+                },
+                ..Default::default()
+            }],
+            ..Default::default()
+        },
+    )
+    .unwrap();
+
+    queue.submit(renderer.resize(&device, WIDTH, HEIGHT));
+
+    let mut sequence = PngSequence::new(&dir).unwrap();
+    let delta = Duration::from_secs_f64(1.0 / 60.0);
+
+    for frame in 0..frame_count {
+        let t = frame as f32 * delta.as_secs_f32();
+        let global_height = (t * 4.0).sin() * 0.2 + 0.4;
+        let freq_data: Vec<f32> = (0..32)
+            .map(|f| (0.5 * f as f32 + t).sin() * 0.2 + global_height)
+            .map(|f| f.clamp(0.0, 1.0))
+            .collect();
+
+        renderer.update(delta, &freq_data);
+        queue.submit(renderer.render(&device, &target.view));
+        queue.submit(target.copy(&device));
+
+        let pixels = block_on(target.read(&device)).unwrap();
+        sequence.write(WIDTH, HEIGHT, &pixels).unwrap();
+    }
+
+    println!("wrote {} frames to {}", sequence.len(), dir);
+}
@@ -1,19 +1,95 @@
-use super::render_target::{RenderTarget, RenderTargetFamily};
+use super::{
+    render_target::{RenderTarget, RenderTargetFamily},
+    SettingsError,
+};
+use std::future::Future;
 
-#[derive(Debug, Clone)]
+const MAX_ITERATIONS: u32 = 8;
+const MAX_DOWNSAMPLE: u32 = 16;
+
+/// How the trails are blurred each frame, before they fade into the next.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum BlurKernel {
+    /// A horizontal then a vertical 9-tap Gaussian, per iteration.
+    Gaussian9,
+    /// A horizontal then a vertical 13-tap Gaussian, per iteration. Wider than the 9-tap one.
+    Gaussian13,
+    /// A horizontal then a vertical pass averaging 9 texels, per iteration. Blockier, and cheap.
+    Box,
+    /// Halves the resolution once per iteration and scales back up, spreading much further than
+    /// the others for the same number of passes.
+    DualKawase,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct Blur {
+    pub kernel: BlurKernel,
+    /// Multiplies the distance between the kernel's samples.
+    pub radius: f32,
+    /// Number of times the kernel is applied, between 1 and 8.
+    pub iterations: u32,
+    /// How many times smaller than the frame the blur is rendered, between 1 and 16. Higher is
+    /// hazier and cheaper.
+    pub downsample: u32,
+}
+
+impl Blur {
+    pub fn validate(&self) -> Result<(), SettingsError> {
+        if !self.radius.is_finite() {
+            return Err(SettingsError::NotFinite("blur.radius"));
+        }
+
+        if self.radius < 0.0
+            || !(1..=MAX_ITERATIONS).contains(&self.iterations)
+            || !(1..=MAX_DOWNSAMPLE).contains(&self.downsample)
+        {
+            return Err(SettingsError::InvalidBlur(self.clone()));
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for Blur {
+    fn default() -> Self {
+        Self {
+            kernel: BlurKernel::Gaussian13,
+            radius: 1.0,
+            iterations: 1,
+            downsample: 2,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 struct Uniforms {
     frame_size: (f32, f32),
+    radius: f32,
+    kernel: u32,
 }
 
 impl Uniforms {
     fn raw(&self) -> [u8; std::mem::size_of::<Self>()] {
-        bytemuck::cast([self.frame_size.0, self.frame_size.1])
+        bytemuck::cast([
+            self.frame_size.0.to_bits(),
+            self.frame_size.1.to_bits(),
+            self.radius.to_bits(),
+            self.kernel,
+        ])
     }
 }
 
-pub enum BlurDirection {
-    Horizontal,
-    Vertical,
+// drawn as the instance index, which the shaders read it from
+#[derive(Debug, Clone, Copy)]
+enum Pass {
+    Horizontal = 0,
+    Vertical = 1,
+    Downsample = 2,
+    Upsample = 3,
 }
 
 pub struct BlurRenderer {
@@ -21,10 +97,20 @@ pub struct BlurRenderer {
     bind_group: wgpu::BindGroup,
     staging_belt: wgpu::util::StagingBelt,
     uniform_buf: wgpu::Buffer,
+    // what the uniforms hold, to only upload changes
+    uniforms: Option<Uniforms>,
+    // created by the render, as their number and sizes depend on the settings
+    targets: Vec<RenderTarget>,
+    frame_size: (u32, u32),
 }
 
 impl BlurRenderer {
-    pub fn new(device: &wgpu::Device, family: &RenderTargetFamily) -> Self {
+    pub fn new(
+        device: &wgpu::Device,
+        family: &RenderTargetFamily,
+        width: u32,
+        height: u32,
+    ) -> Self {
         let vs_module = device.create_shader_module(wgpu::include_spirv!("shaders/blur.vert.spv"));
         let fs_module = device.create_shader_module(wgpu::include_spirv!("shaders/blur.frag.spv"));
 
@@ -103,16 +189,116 @@ impl BlurRenderer {
             bind_group,
             staging_belt,
             uniform_buf,
+            uniforms: None,
+            targets: Vec::new(),
+            frame_size: (width, height),
         }
     }
 
-    pub fn resize(
+    /// Gets the upload buffers back for reuse, like `Compositor::recall`.
+    pub fn recall(&mut self) -> impl Future<Output = ()> + Send {
+        self.staging_belt.recall()
+    }
+
+    /// Takes effect on the next render, which creates targets of the new size.
+    pub fn resize(&mut self, width: u32, height: u32) {
+        self.frame_size = (width, height);
+    }
+
+    /// Blurs `source` into one of the renderer's own targets, and returns it.
+    pub fn render(
         &mut self,
         device: &wgpu::Device,
         encoder: &mut wgpu::CommandEncoder,
-        width: u32,
-        height: u32,
+        family: &RenderTargetFamily,
+        source: &RenderTarget,
+        settings: &Blur,
+    ) -> &RenderTarget {
+        let width = (self.frame_size.0 / settings.downsample).max(1);
+        let height = (self.frame_size.1 / settings.downsample).max(1);
+        let sizes = match settings.kernel {
+            // one more level than iterations, each half the size of the previous one
+            BlurKernel::DualKawase => (0..=settings.iterations)
+                .map(|level| ((width >> level).max(1), (height >> level).max(1)))
+                .collect(),
+            // ping-ponging between the two
+            _ => vec![(width, height); 2],
+        };
+
+        if !self
+            .targets
+            .iter()
+            .map(|target| (target.width, target.height))
+            .eq(sizes.iter().copied())
+        {
+            self.targets = sizes
+                .iter()
+                .map(|&(width, height)| family.create_target(device, width, height))
+                .collect();
+        }
+
+        self.write_uniforms(
+            device,
+            encoder,
+            Uniforms {
+                frame_size: (width as f32, height as f32),
+                radius: settings.radius,
+                kernel: match settings.kernel {
+                    BlurKernel::Gaussian9 => 0,
+                    BlurKernel::Gaussian13 => 1,
+                    BlurKernel::Box => 2,
+                    BlurKernel::DualKawase => 3,
+                },
+            },
+        );
+
+        match settings.kernel {
+            BlurKernel::DualKawase => {
+                let levels = self.targets.len();
+
+                self.pass(encoder, source, &self.targets[0], Pass::Downsample);
+
+                for level in 1..levels {
+                    let (smaller, larger) = (&self.targets[level], &self.targets[level - 1]);
+
+                    self.pass(encoder, larger, smaller, Pass::Downsample);
+                }
+
+                for level in (0..levels - 1).rev() {
+                    let (smaller, larger) = (&self.targets[level + 1], &self.targets[level]);
+
+                    self.pass(encoder, smaller, larger, Pass::Upsample);
+                }
+
+                &self.targets[0]
+            }
+            _ => {
+                for iteration in 0..settings.iterations {
+                    let input = if iteration == 0 {
+                        source
+                    } else {
+                        &self.targets[1]
+                    };
+
+                    self.pass(encoder, input, &self.targets[0], Pass::Horizontal);
+                    self.pass(encoder, &self.targets[0], &self.targets[1], Pass::Vertical);
+                }
+
+                &self.targets[1]
+            }
+        }
+    }
+
+    fn write_uniforms(
+        &mut self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        uniforms: Uniforms,
     ) {
+        if self.uniforms.as_ref() == Some(&uniforms) {
+            return;
+        }
+
         self.staging_belt
             .write_buffer(
                 encoder,
@@ -121,26 +307,22 @@ impl BlurRenderer {
                 wgpu::BufferSize::new(std::mem::size_of::<Uniforms>() as u64).unwrap(),
                 device,
             )
-            .copy_from_slice(
-                &Uniforms {
-                    frame_size: (width as f32, height as f32),
-                }
-                .raw(),
-            );
+            .copy_from_slice(&uniforms.raw());
 
         self.staging_belt.finish();
+        self.uniforms = Some(uniforms);
     }
 
-    pub fn render(
-        &mut self,
+    fn pass(
+        &self,
         encoder: &mut wgpu::CommandEncoder,
         source: &RenderTarget,
-        dest_view: &wgpu::TextureView,
-        dir: BlurDirection,
+        dest: &RenderTarget,
+        pass: Pass,
     ) {
         let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
             color_attachments: &[wgpu::RenderPassColorAttachmentDescriptor {
-                attachment: dest_view,
+                attachment: &dest.view,
                 resolve_target: None,
                 ops: wgpu::Operations {
                     load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
@@ -153,12 +335,6 @@ impl BlurRenderer {
         rpass.set_pipeline(&self.render_pipeline);
         rpass.set_bind_group(0, &self.bind_group, &[]);
         rpass.set_bind_group(1, &source.bind_group, &[]);
-        rpass.draw(
-            0..4,
-            match dir {
-                BlurDirection::Horizontal => 0..1,
-                BlurDirection::Vertical => 1..2,
-            },
-        );
+        rpass.draw(0..4, pass as u32..pass as u32 + 1);
     }
 }
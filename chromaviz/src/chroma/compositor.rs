@@ -1,16 +1,58 @@
-use super::render_target::{RenderTarget, RenderTargetFamily};
+use super::{
+    render_target::{RenderTarget, RenderTargetFamily},
+    Tonemap, Tonemapping,
+};
+use std::future::Future;
+
+#[derive(Debug, Clone)]
+struct Uniforms {
+    exposure: f32,
+    operator: u32,
+}
+
+impl Uniforms {
+    fn raw(&self) -> [u8; std::mem::size_of::<Self>()] {
+        bytemuck::cast([self.exposure.to_bits(), self.operator])
+    }
+}
 
 pub struct Compositor {
     transparent_pipeline: wgpu::RenderPipeline,
     solid_pipeline: wgpu::RenderPipeline,
+    bind_group: wgpu::BindGroup,
+    staging_belt: wgpu::util::StagingBelt,
+    uniform_buf: wgpu::Buffer,
+    // what the uniforms hold, to only upload changes
+    tonemapping: Option<Tonemapping>,
 }
 
 impl Compositor {
-    pub fn new(device: &wgpu::Device, family: &RenderTargetFamily) -> Self {
+    /// Composites within `family`, except for [`render_solid`](Self::render_solid) which tonemaps
+    /// into views of `output_format`.
+    pub fn new(
+        device: &wgpu::Device,
+        family: &RenderTargetFamily,
+        output_format: wgpu::TextureFormat,
+    ) -> Self {
         let vs_module =
             device.create_shader_module(wgpu::include_spirv!("shaders/compositor.vert.spv"));
         let fs_module =
             device.create_shader_module(wgpu::include_spirv!("shaders/compositor.frag.spv"));
+        let tonemap_module =
+            device.create_shader_module(wgpu::include_spirv!("shaders/tonemap.frag.spv"));
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: None,
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStage::FRAGMENT,
+                ty: wgpu::BindingType::UniformBuffer {
+                    dynamic: false,
+                    min_binding_size: wgpu::BufferSize::new(std::mem::size_of::<Uniforms>() as u64),
+                },
+                count: None,
+            }],
+        });
 
         let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
             label: None,
@@ -18,6 +60,29 @@ impl Compositor {
             push_constant_ranges: &[],
         });
 
+        let solid_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: None,
+                bind_group_layouts: &[&bind_group_layout, &family.bind_group_layout],
+                push_constant_ranges: &[],
+            });
+
+        let uniform_buf = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("uniform buffer"),
+            usage: wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST,
+            size: std::mem::size_of::<Uniforms>() as u64,
+            mapped_at_creation: false,
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout: &bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::Buffer(uniform_buf.slice(..)),
+            }],
+        });
+
         let transparent_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
             label: None,
             layout: Some(&pipeline_layout),
@@ -61,13 +126,13 @@ impl Compositor {
 
         let solid_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
             label: None,
-            layout: Some(&pipeline_layout),
+            layout: Some(&solid_pipeline_layout),
             vertex_stage: wgpu::ProgrammableStageDescriptor {
                 module: &vs_module,
                 entry_point: "main",
             },
             fragment_stage: Some(wgpu::ProgrammableStageDescriptor {
-                module: &fs_module,
+                module: &tonemap_module,
                 entry_point: "main",
             }),
             rasterization_state: Some(wgpu::RasterizationStateDescriptor {
@@ -77,7 +142,7 @@ impl Compositor {
             }),
             primitive_topology: wgpu::PrimitiveTopology::TriangleStrip,
             color_states: &[wgpu::ColorStateDescriptor {
-                format: family.format,
+                format: output_format,
                 color_blend: wgpu::BlendDescriptor::REPLACE,
                 alpha_blend: wgpu::BlendDescriptor::REPLACE,
                 write_mask: wgpu::ColorWrite::ALL,
@@ -92,9 +157,15 @@ impl Compositor {
             alpha_to_coverage_enabled: false,
         });
 
+        let staging_belt = wgpu::util::StagingBelt::new(0x100);
+
         Self {
             transparent_pipeline,
             solid_pipeline,
+            bind_group,
+            staging_belt,
+            uniform_buf,
+            tonemapping: None,
         }
     }
 
@@ -128,12 +199,17 @@ impl Compositor {
         rpass.draw(0..4, 0..1);
     }
 
+    /// Replaces `dest_view` with `source`, tonemapped to fit the output.
     pub fn render_solid(
         &mut self,
+        device: &wgpu::Device,
         encoder: &mut wgpu::CommandEncoder,
         source: &RenderTarget,
         dest_view: &wgpu::TextureView,
+        tonemapping: &Tonemapping,
     ) {
+        self.write_uniforms(device, encoder, tonemapping);
+
         let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
             color_attachments: &[wgpu::RenderPassColorAttachmentDescriptor {
                 attachment: dest_view,
@@ -147,7 +223,48 @@ impl Compositor {
         });
 
         rpass.set_pipeline(&self.solid_pipeline);
-        rpass.set_bind_group(0, &source.bind_group, &[]);
+        rpass.set_bind_group(0, &self.bind_group, &[]);
+        rpass.set_bind_group(1, &source.bind_group, &[]);
         rpass.draw(0..4, 0..1);
     }
+
+    /// Gets the upload buffers back for reuse, once the commands written with them are submitted.
+    /// They only come back when the returned future completes, after the GPU is done with them.
+    pub fn recall(&mut self) -> impl Future<Output = ()> + Send {
+        self.staging_belt.recall()
+    }
+
+    fn write_uniforms(
+        &mut self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        tonemapping: &Tonemapping,
+    ) {
+        if self.tonemapping.as_ref() == Some(tonemapping) {
+            return;
+        }
+
+        self.staging_belt
+            .write_buffer(
+                encoder,
+                &self.uniform_buf,
+                0,
+                wgpu::BufferSize::new(std::mem::size_of::<Uniforms>() as u64).unwrap(),
+                device,
+            )
+            .copy_from_slice(
+                &Uniforms {
+                    exposure: tonemapping.exposure,
+                    operator: match tonemapping.operator {
+                        Tonemap::Clamp => 0,
+                        Tonemap::Reinhard => 1,
+                        Tonemap::Aces => 2,
+                    },
+                }
+                .raw(),
+            );
+
+        self.staging_belt.finish();
+        self.tonemapping = Some(tonemapping.clone());
+    }
 }
@@ -0,0 +1,680 @@
+use super::{
+    render_target::{RenderTarget, RenderTargetFamily},
+    SettingsError,
+};
+use std::{borrow::Cow, future::Future, path::PathBuf, time::Duration};
+
+// the uniforms, padded to the 16 bytes uniform blocks are rounded up to
+const UNIFORMS_SIZE: u64 = 32;
+// texels in a row of the band texture must add up to a multiple of this many bytes to upload it
+const ROW_ALIGNMENT: u32 = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+
+/// Declarations put before every custom shader, documented on [`CustomShader`].
+pub const PRELUDE: &str = r#"#version 450
+
+layout(set = 0, binding = 0) uniform Globals {
+    vec2 u_Resolution;
+    float u_Time;
+    float u_Delta;
+    uint u_Bands;
+};
+layout(set = 0, binding = 1) uniform texture1D t_Bands;
+layout(set = 0, binding = 2) uniform sampler s_Bands;
+layout(set = 1, binding = 5) uniform texture2D t_Color;
+layout(set = 1, binding = 6) uniform sampler s_Color;
+layout(set = 2, binding = 5) uniform texture2D t_Accumulator;
+layout(set = 2, binding = 6) uniform sampler s_Accumulator;
+
+layout(location = 0) in vec2 v_TexCoord;
+layout(location = 0) out vec4 outColor;
+
+float band(float freq) {
+    if (u_Bands == 0u) {
+        return 0.0;
+    }
+
+    int i = clamp(int(freq * float(u_Bands)), 0, int(u_Bands) - 1);
+
+    return texelFetch(sampler1D(t_Bands, s_Bands), i, 0).r;
+}
+"#;
+
+/// A GLSL fragment shader loaded at runtime, drawn over the whole frame after the post effects
+/// and before tonemapping.
+///
+/// The file holds the body of the shader, without a `#version`: [`PRELUDE`] comes before it,
+/// declaring everything it can use:
+///
+/// - `u_Resolution`: size of the frame, in pixels.
+/// - `u_Time`: seconds since the start.
+/// - `u_Delta`: seconds since the previous frame.
+/// - `u_Bands`: number of bands in `t_Bands`.
+/// - `t_Bands` and `s_Bands`: the current level of each band in the red channel, from the lowest
+///   at texel 0. `band(freq)` reads the one at `freq`, from 0 for the lowest to 1 for the highest.
+/// - `t_Color` and `s_Color`: what the previous pass drew.
+/// - `t_Accumulator` and `s_Accumulator`: the trails as they feed back into the next frame,
+///   before any post effect.
+/// - `v_TexCoord`: where the pixel is, from (0, 0) at the top left to (1, 1) at the bottom right.
+/// - `outColor`: the pixel's color, replacing it.
+///
+/// Shaders can't declare textures, samplers or uniforms of their own.
+///
+/// Shaders need the `glsl` feature to compile, and are compiled again every time they are given
+/// to [`Chroma`](super::Chroma).
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CustomShader {
+    pub path: PathBuf,
+    /// Skips the shader when false, without compiling it.
+    #[cfg_attr(feature = "serde", serde(default = "super::effects::enabled"))]
+    pub enabled: bool,
+}
+
+impl CustomShader {
+    /// Reads the shader and compiles it to SPIR-V.
+    pub fn compile(&self) -> Result<Vec<u32>, SettingsError> {
+        let error = |reason: String| SettingsError::CustomShader {
+            path: self.path.clone(),
+            reason,
+        };
+        let source = std::fs::read_to_string(&self.path).map_err(|e| error(e.to_string()))?;
+
+        compile(&source).map_err(error)
+    }
+}
+
+impl From<PathBuf> for CustomShader {
+    /// Enabled.
+    fn from(path: PathBuf) -> Self {
+        Self {
+            path,
+            enabled: true,
+        }
+    }
+}
+
+#[cfg(feature = "glsl")]
+fn compile(source: &str) -> Result<Vec<u32>, String> {
+    use glslang::{Compiler, CompilerOptions, Shader, ShaderInput, ShaderSource, ShaderStage};
+
+    let compiler = Compiler::acquire().ok_or("glslang failed to initialize")?;
+    // line numbers in errors are those of the file
+    let source = ShaderSource::from(format!("{}#line 1\n{}", PRELUDE, source));
+    let input = ShaderInput::new(
+        &source,
+        ShaderStage::Fragment,
+        &CompilerOptions::default(),
+        None::<&[(&str, Option<&str>)]>,
+        None,
+    )
+    .map_err(|e| e.to_string())?;
+
+    let spirv = Shader::new(compiler, input)
+        .and_then(|shader| shader.compile())
+        .map_err(|e| e.to_string())?;
+
+    check_resources(&spirv)?;
+    Ok(spirv)
+}
+
+// what the pipeline layout binds, as declared by the prelude
+#[cfg(feature = "glsl")]
+#[derive(Debug, PartialEq)]
+enum Resource {
+    Uniforms,
+    Texture { dim: u32 },
+    Sampler,
+    Unsupported,
+}
+
+#[cfg(feature = "glsl")]
+impl Resource {
+    const DIM_1D: u32 = 0;
+    const DIM_2D: u32 = 1;
+
+    fn at(set: u32, binding: u32) -> Option<Self> {
+        match (set, binding) {
+            (0, 0) => Some(Resource::Uniforms),
+            (0, 1) => Some(Resource::Texture { dim: Self::DIM_1D }),
+            (1, 5) | (2, 5) => Some(Resource::Texture { dim: Self::DIM_2D }),
+            (0, 2) | (1, 6) | (2, 6) => Some(Resource::Sampler),
+            _ => None,
+        }
+    }
+}
+
+// Resources that don't match the pipeline layout make wgpu panic when creating the pipeline, so
+// they're rejected here with the other errors of the shader.
+#[cfg(feature = "glsl")]
+fn check_resources(spirv: &[u32]) -> Result<(), String> {
+    use std::collections::HashMap;
+
+    const OP_TYPE_FLOAT: u32 = 22;
+    const OP_TYPE_IMAGE: u32 = 25;
+    const OP_TYPE_SAMPLER: u32 = 26;
+    const OP_TYPE_STRUCT: u32 = 30;
+    const OP_TYPE_POINTER: u32 = 32;
+    const OP_VARIABLE: u32 = 59;
+    const OP_DECORATE: u32 = 71;
+    const DECORATION_BINDING: u32 = 33;
+    const DECORATION_DESCRIPTOR_SET: u32 = 34;
+    const STORAGE_UNIFORM_CONSTANT: u32 = 0;
+    const STORAGE_UNIFORM: u32 = 2;
+    const STORAGE_PUSH_CONSTANT: u32 = 9;
+    const STORAGE_STORAGE_BUFFER: u32 = 12;
+
+    let mut types = HashMap::new();
+    let mut pointers = HashMap::new();
+    let mut sets = HashMap::new();
+    let mut bindings = HashMap::new();
+    let mut variables = Vec::new();
+    // past the header
+    let mut i = 5;
+
+    while i < spirv.len() {
+        let len = (spirv[i] >> 16) as usize;
+
+        if len == 0 || i + len > spirv.len() {
+            return Err("glslang produced invalid SPIR-V".to_owned());
+        }
+
+        let opcode = spirv[i] & 0xffff;
+        let operands = &spirv[i + 1..i + len];
+
+        match opcode {
+            OP_TYPE_FLOAT | OP_TYPE_IMAGE | OP_TYPE_SAMPLER | OP_TYPE_STRUCT => {
+                types.insert(operands[0], (opcode, operands));
+            }
+            OP_TYPE_POINTER => {
+                pointers.insert(operands[0], operands[2]);
+            }
+            // result type, result and storage class
+            OP_VARIABLE => variables.push((operands[1], operands[0], operands[2])),
+            OP_DECORATE if operands[1] == DECORATION_DESCRIPTOR_SET => {
+                sets.insert(operands[0], operands[2]);
+            }
+            OP_DECORATE if operands[1] == DECORATION_BINDING => {
+                bindings.insert(operands[0], operands[2]);
+            }
+            _ => (),
+        }
+
+        i += len;
+    }
+
+    let mut uniform_blocks = 0;
+
+    for (variable, pointer, storage) in variables {
+        let ty = pointers.get(&pointer).and_then(|ty| types.get(ty));
+        let resource = match (storage, ty) {
+            (STORAGE_PUSH_CONSTANT, _) => return Err("push constants aren't supported".to_owned()),
+            (STORAGE_UNIFORM, Some(&(OP_TYPE_STRUCT, _))) => {
+                uniform_blocks += 1;
+                Resource::Uniforms
+            }
+            // float sampled type, and neither arrayed nor multisampled
+            (STORAGE_UNIFORM_CONSTANT, Some(&(OP_TYPE_IMAGE, image)))
+                if matches!(types.get(&image[1]), Some(&(OP_TYPE_FLOAT, _)))
+                    && image[4] == 0
+                    && image[5] == 0 =>
+            {
+                Resource::Texture { dim: image[2] }
+            }
+            (STORAGE_UNIFORM_CONSTANT, Some(&(OP_TYPE_SAMPLER, _))) => Resource::Sampler,
+            (STORAGE_UNIFORM_CONSTANT, _) | (STORAGE_UNIFORM, _) | (STORAGE_STORAGE_BUFFER, _) => {
+                Resource::Unsupported
+            }
+            _ => continue,
+        };
+        let set = sets.get(&variable).copied().unwrap_or(0);
+        let binding = bindings.get(&variable).copied().unwrap_or(0);
+
+        if Resource::at(set, binding).as_ref() != Some(&resource) {
+            return Err(format!(
+                "set {}, binding {} doesn't match the prelude's bindings",
+                set, binding
+            ));
+        }
+    }
+
+    if uniform_blocks > 1 {
+        return Err("uniform blocks besides the prelude's aren't supported".to_owned());
+    }
+
+    Ok(())
+}
+
+#[cfg(not(feature = "glsl"))]
+fn compile(_source: &str) -> Result<Vec<u32>, String> {
+    Err("chromaviz was built without the glsl feature".to_owned())
+}
+
+/// Compiles the enabled shaders, giving `None` for the others.
+pub fn compile_all(shaders: &[CustomShader]) -> Result<Vec<Option<Vec<u32>>>, SettingsError> {
+    shaders
+        .iter()
+        .map(|shader| {
+            if shader.enabled {
+                shader.compile().map(Some)
+            } else {
+                Ok(None)
+            }
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone)]
+struct Uniforms {
+    resolution: (f32, f32),
+    time: f32,
+    delta: f32,
+    bands: u32,
+}
+
+impl Uniforms {
+    fn raw(&self) -> [u8; UNIFORMS_SIZE as usize] {
+        bytemuck::cast([
+            self.resolution.0.to_bits(),
+            self.resolution.1.to_bits(),
+            self.time.to_bits(),
+            self.delta.to_bits(),
+            self.bands,
+            0,
+            0,
+            0,
+        ])
+    }
+}
+
+pub struct CustomShaderRenderer {
+    vs_module: wgpu::ShaderModule,
+    pipeline_layout: wgpu::PipelineLayout,
+    bind_group_layout: wgpu::BindGroupLayout,
+    bind_group: wgpu::BindGroup,
+    staging_belt: wgpu::util::StagingBelt,
+    uniform_buf: wgpu::Buffer,
+    band_sampler: wgpu::Sampler,
+    band_texture: wgpu::Texture,
+    // texels in the band texture, more than there are bands to keep rows aligned
+    band_capacity: u32,
+    // one per shader, `None` for disabled ones
+    pipelines: Vec<Option<wgpu::RenderPipeline>>,
+    // compiled by `set_shaders`, for the next render to create pipelines from
+    pending: Option<Vec<Option<Vec<u32>>>>,
+    // created by the first render with shaders, to ping-pong between
+    targets: Vec<RenderTarget>,
+    frame_size: (u32, u32),
+    time: Duration,
+    delta: Duration,
+    bands: Vec<f32>,
+}
+
+impl CustomShaderRenderer {
+    pub fn new(
+        device: &wgpu::Device,
+        family: &RenderTargetFamily,
+        width: u32,
+        height: u32,
+    ) -> Self {
+        let vs_module =
+            device.create_shader_module(wgpu::include_spirv!("shaders/compositor.vert.spv"));
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: None,
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStage::FRAGMENT,
+                    ty: wgpu::BindingType::UniformBuffer {
+                        dynamic: false,
+                        min_binding_size: wgpu::BufferSize::new(UNIFORMS_SIZE),
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStage::FRAGMENT,
+                    ty: wgpu::BindingType::SampledTexture {
+                        dimension: wgpu::TextureViewDimension::D1,
+                        component_type: wgpu::TextureComponentType::Float,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStage::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler { comparison: false },
+                    count: None,
+                },
+            ],
+        });
+
+        // the previous pass, then the accumulator
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[
+                &bind_group_layout,
+                &family.bind_group_layout,
+                &family.bind_group_layout,
+            ],
+            push_constant_ranges: &[],
+        });
+
+        let uniform_buf = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("custom shader uniform buffer"),
+            usage: wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST,
+            size: UNIFORMS_SIZE,
+            mapped_at_creation: false,
+        });
+
+        // bands are read texel by texel
+        let band_sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Nearest,
+            min_filter: wgpu::FilterMode::Nearest,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+
+        let band_capacity = ROW_ALIGNMENT / 4;
+        let band_texture = create_band_texture(device, band_capacity);
+        let bind_group = create_bind_group(
+            device,
+            &bind_group_layout,
+            &uniform_buf,
+            &band_texture,
+            &band_sampler,
+        );
+
+        let staging_belt = wgpu::util::StagingBelt::new(0x100);
+
+        Self {
+            vs_module,
+            pipeline_layout,
+            bind_group_layout,
+            bind_group,
+            staging_belt,
+            uniform_buf,
+            band_sampler,
+            band_texture,
+            band_capacity,
+            pipelines: Vec::new(),
+            pending: None,
+            targets: Vec::new(),
+            frame_size: (width, height),
+            time: Duration::default(),
+            delta: Duration::default(),
+            bands: Vec::new(),
+        }
+    }
+
+    /// Replaces the shaders with compiled ones, from [`compile_all`], on the next render.
+    pub fn set_shaders(&mut self, shaders: Vec<Option<Vec<u32>>>) {
+        self.pending = Some(shaders);
+    }
+
+    /// Gets the upload buffers back for reuse, like `Compositor::recall`.
+    pub fn recall(&mut self) -> impl Future<Output = ()> + Send {
+        self.staging_belt.recall()
+    }
+
+    /// Takes effect on the next render, which creates targets of the new size.
+    pub fn resize(&mut self, width: u32, height: u32) {
+        self.frame_size = (width, height);
+        self.targets.clear();
+    }
+
+    /// `freq_data` holds the level of each band, across the whole spectrum.
+    pub fn update(&mut self, delta: Duration, freq_data: &[f32]) {
+        self.time += delta;
+        self.delta = delta;
+        self.bands.clear();
+        self.bands.extend_from_slice(freq_data);
+    }
+
+    /// Draws the enabled shaders over `source` in order, and returns where the result is: one of
+    /// the renderer's own targets, or `source` itself if there are none.
+    pub fn render<'a>(
+        &'a mut self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        family: &RenderTargetFamily,
+        source: &'a RenderTarget,
+        accumulator: &RenderTarget,
+        shaders: &[CustomShader],
+    ) -> &'a RenderTarget {
+        if let Some(pending) = self.pending.take() {
+            self.pipelines = pending
+                .iter()
+                .map(|spirv| {
+                    spirv
+                        .as_ref()
+                        .map(|spirv| self.create_pipeline(device, family, spirv))
+                })
+                .collect();
+        }
+
+        let passes = self
+            .pipelines
+            .iter()
+            .zip(shaders)
+            .filter(|(pipeline, shader)| shader.enabled && pipeline.is_some())
+            .count();
+
+        if passes == 0 {
+            return source;
+        }
+
+        if self.targets.is_empty() {
+            let (width, height) = self.frame_size;
+
+            self.targets = (0..2)
+                .map(|_| family.create_target(device, width, height))
+                .collect();
+        }
+
+        self.upload(device, encoder);
+
+        let pipelines = self
+            .pipelines
+            .iter()
+            .zip(shaders)
+            .filter(|(_, shader)| shader.enabled)
+            .filter_map(|(pipeline, _)| pipeline.as_ref());
+
+        for (i, pipeline) in pipelines.enumerate() {
+            let input = if i == 0 {
+                source
+            } else {
+                &self.targets[(i - 1) % 2]
+            };
+            let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                color_attachments: &[wgpu::RenderPassColorAttachmentDescriptor {
+                    attachment: &self.targets[i % 2].view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                        store: true,
+                    },
+                }],
+                depth_stencil_attachment: None,
+            });
+
+            rpass.set_pipeline(pipeline);
+            rpass.set_bind_group(0, &self.bind_group, &[]);
+            rpass.set_bind_group(1, &input.bind_group, &[]);
+            rpass.set_bind_group(2, &accumulator.bind_group, &[]);
+            rpass.draw(0..4, 0..1);
+        }
+
+        &self.targets[(passes - 1) % 2]
+    }
+
+    fn create_pipeline(
+        &self,
+        device: &wgpu::Device,
+        family: &RenderTargetFamily,
+        spirv: &[u32],
+    ) -> wgpu::RenderPipeline {
+        let fs_module =
+            device.create_shader_module(wgpu::ShaderModuleSource::SpirV(Cow::Borrowed(spirv)));
+
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("custom shader"),
+            layout: Some(&self.pipeline_layout),
+            vertex_stage: wgpu::ProgrammableStageDescriptor {
+                module: &self.vs_module,
+                entry_point: "main",
+            },
+            fragment_stage: Some(wgpu::ProgrammableStageDescriptor {
+                module: &fs_module,
+                entry_point: "main",
+            }),
+            rasterization_state: Some(wgpu::RasterizationStateDescriptor {
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: wgpu::CullMode::Back,
+                ..Default::default()
+            }),
+            primitive_topology: wgpu::PrimitiveTopology::TriangleStrip,
+            color_states: &[wgpu::ColorStateDescriptor {
+                format: family.format,
+                color_blend: wgpu::BlendDescriptor::REPLACE,
+                alpha_blend: wgpu::BlendDescriptor::REPLACE,
+                write_mask: wgpu::ColorWrite::ALL,
+            }],
+            depth_stencil_state: None,
+            vertex_state: wgpu::VertexStateDescriptor {
+                index_format: wgpu::IndexFormat::Uint16,
+                vertex_buffers: &[],
+            },
+            sample_count: 1,
+            sample_mask: !0,
+            alpha_to_coverage_enabled: false,
+        })
+    }
+
+    // writes the uniforms and the band levels of the frame
+    fn upload(&mut self, device: &wgpu::Device, encoder: &mut wgpu::CommandEncoder) {
+        let bands = self.bands.len() as u32;
+
+        if bands > self.band_capacity {
+            let texels_per_row = ROW_ALIGNMENT / 4;
+
+            self.band_capacity = (bands + texels_per_row - 1) / texels_per_row * texels_per_row;
+            self.band_texture = create_band_texture(device, self.band_capacity);
+            self.bind_group = create_bind_group(
+                device,
+                &self.bind_group_layout,
+                &self.uniform_buf,
+                &self.band_texture,
+                &self.band_sampler,
+            );
+        }
+
+        self.staging_belt
+            .write_buffer(
+                encoder,
+                &self.uniform_buf,
+                0,
+                wgpu::BufferSize::new(UNIFORMS_SIZE).unwrap(),
+                device,
+            )
+            .copy_from_slice(
+                &Uniforms {
+                    resolution: (self.frame_size.0 as f32, self.frame_size.1 as f32),
+                    time: self.time.as_secs_f32(),
+                    delta: self.delta.as_secs_f32(),
+                    // `band` reads a silent one if there are none
+                    bands: bands.max(1),
+                }
+                .raw(),
+            );
+
+        self.staging_belt.finish();
+
+        let mut texels = vec![0.0_f32; self.band_capacity as usize];
+        texels[..self.bands.len()].copy_from_slice(&self.bands);
+
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("custom shader band upload"),
+            usage: wgpu::BufferUsage::COPY_SRC,
+            size: self.band_capacity as wgpu::BufferAddress * 4,
+            mapped_at_creation: true,
+        });
+
+        buffer
+            .slice(..)
+            .get_mapped_range_mut()
+            .copy_from_slice(bytemuck::cast_slice(&texels));
+        buffer.unmap();
+
+        encoder.copy_buffer_to_texture(
+            wgpu::BufferCopyView {
+                buffer: &buffer,
+                layout: wgpu::TextureDataLayout {
+                    offset: 0,
+                    bytes_per_row: self.band_capacity * 4,
+                    rows_per_image: 1,
+                },
+            },
+            wgpu::TextureCopyView {
+                texture: &self.band_texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+            },
+            wgpu::Extent3d {
+                width: self.band_capacity,
+                height: 1,
+                depth: 1,
+            },
+        );
+    }
+}
+
+fn create_band_texture(device: &wgpu::Device, capacity: u32) -> wgpu::Texture {
+    device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("custom shader bands"),
+        size: wgpu::Extent3d {
+            width: capacity,
+            height: 1,
+            depth: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D1,
+        format: wgpu::TextureFormat::R32Float,
+        usage: wgpu::TextureUsage::SAMPLED | wgpu::TextureUsage::COPY_DST,
+    })
+}
+
+fn create_bind_group(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    uniform_buf: &wgpu::Buffer,
+    band_texture: &wgpu::Texture,
+    band_sampler: &wgpu::Sampler,
+) -> wgpu::BindGroup {
+    let view = band_texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: None,
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::Buffer(uniform_buf.slice(..)),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::TextureView(&view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: wgpu::BindingResource::Sampler(band_sampler),
+            },
+        ],
+    })
+}
@@ -0,0 +1,506 @@
+use super::{
+    render_target::{RenderTarget, RenderTargetFamily},
+    SettingsError,
+};
+use glam::Vec2;
+use std::{future::Future, time::Duration};
+
+const UNIFORMS_SIZE: u64 = std::mem::size_of::<Uniforms>() as u64;
+// each effect's uniforms are at their own dynamic offset, which must be aligned
+const UNIFORMS_STRIDE: u64 = wgpu::BIND_BUFFER_ALIGNMENT;
+
+/// Darkens the edges of the frame.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct Vignette {
+    /// How dark it gets, from 0 to 1 for black.
+    pub strength: f32,
+    /// Distance from the middle where the darkening starts, 1 being the corners.
+    pub radius: f32,
+    /// Distance over which it fades in.
+    pub softness: f32,
+}
+
+/// Splits red and blue apart towards the edges, like a cheap lens.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct ChromaticAberration {
+    /// How far apart they are in the corners, in frame sizes.
+    pub offset: f32,
+}
+
+/// Flickering film grain.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct Grain {
+    pub strength: f32,
+    /// Width of a grain, in pixels.
+    pub size: f32,
+}
+
+/// Horizontal lines, like an old CRT.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct Scanlines {
+    /// How dark the lines get, from 0 to 1 for black.
+    pub strength: f32,
+    /// Number of lines down the frame.
+    pub count: f32,
+}
+
+/// Mirrors one slice of the frame all the way around a point.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct Kaleidoscope {
+    /// Number of slices, each a mirrored pair of halves. 1 mirrors the frame across a line.
+    pub segments: u32,
+    /// Counterclockwise, in degrees. At 0, the first slice starts to the right of the center.
+    pub rotation: f32,
+    /// From (0, 0) at the bottom left to (1, 1) at the top right.
+    pub center: Vec2,
+}
+
+/// Adjusts colors, before tonemapping.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct ColorGrading {
+    /// Above 1 pushes colors away from middle grey, below 1 pulls them towards it.
+    pub contrast: f32,
+    /// 0 is greyscale, above 1 more colorful.
+    pub saturation: f32,
+    /// Above 1 brightens the darker colors.
+    pub gamma: f32,
+    /// Multiplies the red, green and blue channels.
+    pub tint: [f32; 3],
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum Effect {
+    Vignette(Vignette),
+    ChromaticAberration(ChromaticAberration),
+    Grain(Grain),
+    Scanlines(Scanlines),
+    Kaleidoscope(Kaleidoscope),
+    ColorGrading(ColorGrading),
+}
+
+/// A full-screen effect applied to the frame after the trails, before tonemapping.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PostEffect {
+    /// Skips the effect when false, keeping its settings around.
+    #[cfg_attr(feature = "serde", serde(default = "enabled"))]
+    pub enabled: bool,
+    #[cfg_attr(feature = "serde", serde(flatten))]
+    pub effect: Effect,
+}
+
+#[cfg(feature = "serde")]
+pub(super) fn enabled() -> bool {
+    true
+}
+
+impl Default for Vignette {
+    fn default() -> Self {
+        Self {
+            strength: 0.5,
+            radius: 0.5,
+            softness: 0.5,
+        }
+    }
+}
+
+impl Default for ChromaticAberration {
+    fn default() -> Self {
+        Self { offset: 0.005 }
+    }
+}
+
+impl Default for Grain {
+    fn default() -> Self {
+        Self {
+            strength: 0.05,
+            size: 1.0,
+        }
+    }
+}
+
+impl Default for Scanlines {
+    fn default() -> Self {
+        Self {
+            strength: 0.25,
+            count: 240.0,
+        }
+    }
+}
+
+impl Default for Kaleidoscope {
+    fn default() -> Self {
+        Self {
+            segments: 6,
+            rotation: 0.0,
+            center: (0.5, 0.5).into(),
+        }
+    }
+}
+
+impl Default for ColorGrading {
+    /// Leaves colors as they are.
+    fn default() -> Self {
+        Self {
+            contrast: 1.0,
+            saturation: 1.0,
+            gamma: 1.0,
+            tint: [1.0; 3],
+        }
+    }
+}
+
+impl From<Effect> for PostEffect {
+    /// Enabled.
+    fn from(effect: Effect) -> Self {
+        Self {
+            enabled: true,
+            effect,
+        }
+    }
+}
+
+impl Effect {
+    // whether the parameters, once known to be finite, make sense
+    fn in_range(&self) -> bool {
+        match self {
+            Effect::Vignette(v) => v.strength >= 0.0 && v.radius >= 0.0 && v.softness >= 0.0,
+            Effect::ChromaticAberration(_) => true,
+            Effect::Grain(g) => g.strength >= 0.0 && g.size > 0.0,
+            Effect::Scanlines(s) => (0.0..=1.0).contains(&s.strength) && s.count >= 0.0,
+            Effect::Kaleidoscope(k) => k.segments >= 1,
+            Effect::ColorGrading(c) => {
+                c.contrast >= 0.0
+                    && c.saturation >= 0.0
+                    && c.gamma > 0.0
+                    && c.tint.iter().all(|&v| v >= 0.0)
+            }
+        }
+    }
+
+    // the effect's index and parameters, as in effect.frag
+    fn params(&self) -> (u32, [f32; 8]) {
+        match self {
+            Effect::Vignette(v) => (
+                0,
+                [v.strength, v.radius, v.softness, 0.0, 0.0, 0.0, 0.0, 0.0],
+            ),
+            Effect::ChromaticAberration(c) => (1, [c.offset, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0]),
+            Effect::Grain(g) => (2, [g.strength, g.size, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0]),
+            Effect::Scanlines(s) => (3, [s.strength, s.count, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0]),
+            Effect::Kaleidoscope(k) => (
+                4,
+                [
+                    k.segments as f32,
+                    k.rotation.to_radians(),
+                    k.center.x,
+                    k.center.y,
+                    0.0,
+                    0.0,
+                    0.0,
+                    0.0,
+                ],
+            ),
+            Effect::ColorGrading(c) => (
+                5,
+                [
+                    c.contrast,
+                    c.saturation,
+                    c.gamma,
+                    0.0,
+                    c.tint[0],
+                    c.tint[1],
+                    c.tint[2],
+                    0.0,
+                ],
+            ),
+        }
+    }
+}
+
+/// Checks each effect, disabled ones included.
+pub fn validate(effects: &[PostEffect]) -> Result<(), SettingsError> {
+    for (i, effect) in effects.iter().enumerate() {
+        let (_, params) = effect.effect.params();
+
+        if !params.iter().all(|v| v.is_finite()) {
+            return Err(SettingsError::NotFinite("effects"));
+        }
+
+        if !effect.effect.in_range() {
+            return Err(SettingsError::InvalidEffect(i));
+        }
+    }
+
+    Ok(())
+}
+
+#[derive(Debug, Clone)]
+struct Uniforms {
+    params: [f32; 8],
+    frame_size: (f32, f32),
+    time: f32,
+    effect: u32,
+}
+
+impl Uniforms {
+    fn raw(&self) -> [u8; std::mem::size_of::<Self>()] {
+        let p = self.params;
+
+        bytemuck::cast([
+            p[0].to_bits(),
+            p[1].to_bits(),
+            p[2].to_bits(),
+            p[3].to_bits(),
+            p[4].to_bits(),
+            p[5].to_bits(),
+            p[6].to_bits(),
+            p[7].to_bits(),
+            self.frame_size.0.to_bits(),
+            self.frame_size.1.to_bits(),
+            self.time.to_bits(),
+            self.effect,
+        ])
+    }
+}
+
+pub struct EffectRenderer {
+    render_pipeline: wgpu::RenderPipeline,
+    bind_group_layout: wgpu::BindGroupLayout,
+    bind_group: wgpu::BindGroup,
+    staging_belt: wgpu::util::StagingBelt,
+    uniform_buf: wgpu::Buffer,
+    // number of effects the uniform buffer has room for
+    capacity: usize,
+    // created by the first render with effects, to ping-pong between
+    targets: Vec<RenderTarget>,
+    frame_size: (u32, u32),
+    time: Duration,
+}
+
+impl EffectRenderer {
+    pub fn new(
+        device: &wgpu::Device,
+        family: &RenderTargetFamily,
+        width: u32,
+        height: u32,
+    ) -> Self {
+        let vs_module =
+            device.create_shader_module(wgpu::include_spirv!("shaders/compositor.vert.spv"));
+        let fs_module =
+            device.create_shader_module(wgpu::include_spirv!("shaders/effect.frag.spv"));
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: None,
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStage::FRAGMENT,
+                ty: wgpu::BindingType::UniformBuffer {
+                    dynamic: true,
+                    min_binding_size: wgpu::BufferSize::new(UNIFORMS_SIZE),
+                },
+                count: None,
+            }],
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&bind_group_layout, &family.bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let (uniform_buf, bind_group) = create_uniforms(device, &bind_group_layout, 1);
+
+        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: None,
+            layout: Some(&pipeline_layout),
+            vertex_stage: wgpu::ProgrammableStageDescriptor {
+                module: &vs_module,
+                entry_point: "main",
+            },
+            fragment_stage: Some(wgpu::ProgrammableStageDescriptor {
+                module: &fs_module,
+                entry_point: "main",
+            }),
+            rasterization_state: Some(wgpu::RasterizationStateDescriptor {
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: wgpu::CullMode::Back,
+                ..Default::default()
+            }),
+            primitive_topology: wgpu::PrimitiveTopology::TriangleStrip,
+            color_states: &[wgpu::ColorStateDescriptor {
+                format: family.format,
+                color_blend: wgpu::BlendDescriptor::REPLACE,
+                alpha_blend: wgpu::BlendDescriptor::REPLACE,
+                write_mask: wgpu::ColorWrite::ALL,
+            }],
+            depth_stencil_state: None,
+            vertex_state: wgpu::VertexStateDescriptor {
+                index_format: wgpu::IndexFormat::Uint16,
+                vertex_buffers: &[],
+            },
+            sample_count: 1,
+            sample_mask: !0,
+            alpha_to_coverage_enabled: false,
+        });
+
+        let staging_belt = wgpu::util::StagingBelt::new(0x400);
+
+        Self {
+            render_pipeline,
+            bind_group_layout,
+            bind_group,
+            staging_belt,
+            uniform_buf,
+            capacity: 1,
+            targets: Vec::new(),
+            frame_size: (width, height),
+            time: Duration::default(),
+        }
+    }
+
+    /// Gets the upload buffers back for reuse, like `Compositor::recall`.
+    pub fn recall(&mut self) -> impl Future<Output = ()> + Send {
+        self.staging_belt.recall()
+    }
+
+    /// Takes effect on the next render, which creates targets of the new size.
+    pub fn resize(&mut self, width: u32, height: u32) {
+        self.frame_size = (width, height);
+        self.targets.clear();
+    }
+
+    pub fn update(&mut self, delta: Duration) {
+        self.time += delta;
+    }
+
+    /// Applies the enabled effects to `source` in order, and returns where the result is: one of
+    /// the renderer's own targets, or `source` itself if there are none.
+    pub fn render<'a>(
+        &'a mut self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        family: &RenderTargetFamily,
+        source: &'a RenderTarget,
+        effects: &[PostEffect],
+    ) -> &'a RenderTarget {
+        let enabled: Vec<_> = effects
+            .iter()
+            .filter(|effect| effect.enabled)
+            .map(|effect| effect.effect.params())
+            .collect();
+
+        if enabled.is_empty() {
+            return source;
+        }
+
+        if enabled.len() > self.capacity {
+            let (uniform_buf, bind_group) =
+                create_uniforms(device, &self.bind_group_layout, enabled.len());
+
+            self.uniform_buf = uniform_buf;
+            self.bind_group = bind_group;
+            self.capacity = enabled.len();
+        }
+
+        if self.targets.is_empty() {
+            let (width, height) = self.frame_size;
+
+            self.targets = (0..2)
+                .map(|_| family.create_target(device, width, height))
+                .collect();
+        }
+
+        {
+            let mut buf = self.staging_belt.write_buffer(
+                encoder,
+                &self.uniform_buf,
+                0,
+                wgpu::BufferSize::new(enabled.len() as u64 * UNIFORMS_STRIDE).unwrap(),
+                device,
+            );
+
+            for (i, &(effect, params)) in enabled.iter().enumerate() {
+                let uniforms = Uniforms {
+                    params,
+                    frame_size: (self.frame_size.0 as f32, self.frame_size.1 as f32),
+                    time: self.time.as_secs_f32(),
+                    effect,
+                };
+                let addr = i * UNIFORMS_STRIDE as usize;
+
+                buf[addr..addr + UNIFORMS_SIZE as usize].copy_from_slice(&uniforms.raw());
+            }
+        }
+
+        self.staging_belt.finish();
+
+        for i in 0..enabled.len() {
+            let input = if i == 0 {
+                source
+            } else {
+                &self.targets[(i - 1) % 2]
+            };
+            let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                color_attachments: &[wgpu::RenderPassColorAttachmentDescriptor {
+                    attachment: &self.targets[i % 2].view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                        store: true,
+                    },
+                }],
+                depth_stencil_attachment: None,
+            });
+
+            rpass.set_pipeline(&self.render_pipeline);
+            rpass.set_bind_group(
+                0,
+                &self.bind_group,
+                &[(i as u64 * UNIFORMS_STRIDE) as wgpu::DynamicOffset],
+            );
+            rpass.set_bind_group(1, &input.bind_group, &[]);
+            rpass.draw(0..4, 0..1);
+        }
+
+        &self.targets[(enabled.len() - 1) % 2]
+    }
+}
+
+fn create_uniforms(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    capacity: usize,
+) -> (wgpu::Buffer, wgpu::BindGroup) {
+    let uniform_buf = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("effect uniform buffer"),
+        usage: wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST,
+        size: capacity as u64 * UNIFORMS_STRIDE,
+        mapped_at_creation: false,
+    });
+
+    let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: None,
+        layout,
+        entries: &[wgpu::BindGroupEntry {
+            binding: 0,
+            resource: wgpu::BindingResource::Buffer(uniform_buf.slice(..UNIFORMS_SIZE)),
+        }],
+    });
+
+    (uniform_buf, bind_group)
+}
@@ -0,0 +1,140 @@
+use super::{particle::ParticleSettings, Layout, SettingsError};
+use glam::Vec2;
+use std::{f32::consts::PI, ops::Range};
+
+/// Where along an emitter its frequencies are spread, and which way they launch.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum EmitterShape {
+    /// Frequencies from left to right, launching perpendicular to the line.
+    Line { length: f32 },
+    /// Every frequency from the same point.
+    Point,
+    /// Frequencies all the way around, from the bottom up the left side, launching outwards.
+    Circle { radius: f32 },
+    /// Frequencies from left to right along an arc centered on the top of a circle, launching
+    /// outwards. The angle it spans is in degrees.
+    Arc { radius: f32, angle: f32 },
+}
+
+/// Emits particles from part of the spectrum.
+///
+/// Positions are from (0, 0) at the bottom left of the frame to (1, 1) at the top right, before the
+/// [`layout`](Self::layout) maps them.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct Emitter {
+    pub shape: EmitterShape,
+    /// Center of the shape.
+    pub position: Vec2,
+    /// Counterclockwise rotation of the shape, in degrees. 0 launches particles upwards.
+    pub orientation: f32,
+    /// Positions, orientation and gravity all apply before the layout, so a line across the floor
+    /// in a polar layout goes around its circle and launches outwards.
+    pub layout: Layout,
+    /// Flips the emitter and its particles upside down around the middle of the frame, so that a
+    /// fountain on the floor hangs from the top instead.
+    pub mirrored: bool,
+    /// The part of the spectrum the emitter's frequencies cover, from 0 (the lowest band) to 1
+    /// (the highest).
+    pub frequency_range: Range<f32>,
+    pub particles: ParticleSettings,
+}
+
+impl Emitter {
+    pub fn validate(&self) -> Result<(), SettingsError> {
+        let (size, angle) = match self.shape {
+            EmitterShape::Line { length } => (length, 0.0),
+            EmitterShape::Point => (0.0, 0.0),
+            EmitterShape::Circle { radius } => (radius, 0.0),
+            EmitterShape::Arc { radius, angle } => (radius, angle),
+        };
+        let floats = [
+            ("shape", size),
+            ("shape", angle),
+            ("position", self.position.x),
+            ("position", self.position.y),
+            ("orientation", self.orientation),
+            ("frequency_range", self.frequency_range.start),
+            ("frequency_range", self.frequency_range.end),
+        ];
+
+        if let Some(&(field, _)) = floats.iter().find(|(_, v)| !v.is_finite()) {
+            return Err(SettingsError::NotFinite(field));
+        }
+
+        if size < 0.0 || !(0.0..=360.0).contains(&angle) {
+            return Err(SettingsError::InvalidShape(self.shape.clone()));
+        }
+
+        if self.frequency_range.start > self.frequency_range.end {
+            return Err(SettingsError::ReversedRange("frequency_range"));
+        }
+
+        let in_spectrum = |v: f32| (0.0..=1.0).contains(&v);
+
+        if !in_spectrum(self.frequency_range.start) || !in_spectrum(self.frequency_range.end) {
+            return Err(SettingsError::FrequencyRange(self.frequency_range.clone()));
+        }
+
+        self.layout.validate()?;
+        self.particles.validate()
+    }
+
+    /// Where in the spectrum a frequency of the emitter is, `freq` going from 0 to 1 across the
+    /// emitter.
+    pub fn spectrum_position(&self, freq: f32) -> f32 {
+        let range = &self.frequency_range;
+
+        range.start + freq * (range.end - range.start)
+    }
+
+    /// Where a frequency is emitted from, and the direction it launches towards.
+    pub fn launch(&self, freq: f32) -> (Vec2, Vec2) {
+        let up = Vec2::new(0.0, 1.0);
+        let (offset, direction) = match self.shape {
+            EmitterShape::Line { length } => (Vec2::new((freq - 0.5) * length, 0.0), up),
+            EmitterShape::Point => (Vec2::zero(), up),
+            EmitterShape::Circle { radius } => around(radius, 2.0 * PI, freq),
+            EmitterShape::Arc { radius, angle } => around(radius, angle.to_radians(), freq),
+        };
+        let orientation = self.orientation.to_radians();
+
+        (
+            self.position + rotate(offset, orientation),
+            rotate(direction, orientation),
+        )
+    }
+}
+
+impl Default for Emitter {
+    /// A line across the floor, launching the whole spectrum upwards.
+    fn default() -> Self {
+        Self {
+            shape: EmitterShape::Line { length: 1.0 },
+            position: (0.5, 0.0).into(),
+            orientation: 0.0,
+            layout: Layout::default(),
+            mirrored: false,
+            frequency_range: 0.0..1.0,
+            particles: ParticleSettings::default(),
+        }
+    }
+}
+
+// a point on an arc spanning `angle` radians around the top of a circle, and the outward direction
+fn around(radius: f32, angle: f32, freq: f32) -> (Vec2, Vec2) {
+    // counterclockwise from the top, so that low frequencies are on the left
+    let a = (0.5 - freq) * angle;
+    let direction = Vec2::new(-a.sin(), a.cos());
+
+    (direction * radius, direction)
+}
+
+fn rotate(v: Vec2, angle: f32) -> Vec2 {
+    let (sin, cos) = angle.sin_cos();
+
+    Vec2::new(v.x * cos - v.y * sin, v.x * sin + v.y * cos)
+}
@@ -0,0 +1,144 @@
+use super::{Blur, EmitterShape, Layout};
+use std::{fmt, ops::Range, path::PathBuf};
+
+/// Why a [`ChromaSettings`](super::ChromaSettings) can't be used.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SettingsError {
+    /// A value is NaN or infinite.
+    NotFinite(&'static str),
+    /// `decay` is outside of `0.0..=1.0`.
+    DecayOutOfRange(f64),
+    /// There must be at least 2 frequencies.
+    TooFewFrequencies(u64),
+    /// `particles_per_second` must be between 1 and 1 000 000 000.
+    EmissionRate(u64),
+    /// Gravity must pull particles down, or they never die.
+    UpwardGravity(f32),
+    /// A spread is negative.
+    NegativeSpread(&'static str),
+    /// `size_range` is empty or starts below zero.
+    InvalidSizeRange(Range<f32>),
+    /// `capacity` must be between 1 and 4194304.
+    InvalidCapacity(usize),
+    /// A palette needs at least one color stop.
+    EmptyPalette,
+    /// The color stop at this index is out of order, or has values outside of `0.0..=1.0`.
+    InvalidColorStop(usize),
+    /// A range ends before it starts.
+    ReversedRange(&'static str),
+    /// There must be between 1 and 256 sprites.
+    SpriteCount(usize),
+    /// A sprite image couldn't be loaded.
+    SpriteImage { path: PathBuf, reason: String },
+    /// A drag or turbulence setting is negative.
+    NegativeForce(&'static str),
+    /// `forces.turbulence.modulation` is outside of `0.0..=1.0`.
+    TurbulenceModulation(f32),
+    /// There must be at least one emitter.
+    NoEmitters,
+    /// An emitter shape has a negative size, or an arc spans more than 360 degrees.
+    InvalidShape(EmitterShape),
+    /// An emitter's `frequency_range` goes past `0.0..=1.0`.
+    FrequencyRange(Range<f32>),
+    /// A polar layout has a negative radius.
+    InvalidLayout(Layout),
+    /// `tonemapping.exposure` is negative.
+    NegativeExposure(f32),
+    /// The blur radius is negative, or its iterations or downsample are out of range.
+    InvalidBlur(Blur),
+    /// The feedback zoom is zero or negative, with or without its audio.
+    FeedbackZoom(f32),
+    /// The post effect at this index has a parameter out of range.
+    InvalidEffect(usize),
+    /// A custom shader couldn't be read or compiled.
+    CustomShader { path: PathBuf, reason: String },
+}
+
+impl fmt::Display for SettingsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SettingsError::NotFinite(field) => write!(f, "{} must be a finite number", field),
+            SettingsError::DecayOutOfRange(decay) => {
+                write!(f, "decay must be between 0 and 1, got {}", decay)
+            }
+            SettingsError::TooFewFrequencies(n) => {
+                write!(f, "there must be at least 2 frequencies, got {}", n)
+            }
+            SettingsError::EmissionRate(n) => write!(
+                f,
+                "particles_per_second must be between 1 and 1000000000, got {}",
+                n
+            ),
+            SettingsError::UpwardGravity(y) => {
+                write!(f, "gravity must point downwards, got a y of {}", y)
+            }
+            SettingsError::NegativeSpread(field) => write!(f, "{} must not be negative", field),
+            SettingsError::InvalidSizeRange(range) => write!(
+                f,
+                "size_range must be a non-empty range of positive sizes, got {:?}",
+                range
+            ),
+            SettingsError::InvalidCapacity(n) => {
+                write!(f, "capacity must be between 1 and 4194304, got {}", n)
+            }
+            SettingsError::EmptyPalette => write!(f, "the palette has no color stops"),
+            SettingsError::InvalidColorStop(i) => write!(
+                f,
+                "color stop {} must be in order, with a position and color between 0 and 1",
+                i
+            ),
+            SettingsError::ReversedRange(field) => {
+                write!(f, "{} must not end before it starts", field)
+            }
+            SettingsError::SpriteCount(n) => {
+                write!(f, "there must be between 1 and 256 sprites, got {}", n)
+            }
+            SettingsError::SpriteImage { path, reason } => {
+                write!(f, "couldn't load sprite {}: {}", path.display(), reason)
+            }
+            SettingsError::NegativeForce(field) => write!(f, "{} must not be negative", field),
+            SettingsError::TurbulenceModulation(modulation) => write!(
+                f,
+                "forces.turbulence.modulation must be between 0 and 1, got {}",
+                modulation
+            ),
+            SettingsError::NoEmitters => write!(f, "there must be at least one emitter"),
+            SettingsError::InvalidShape(shape) => write!(
+                f,
+                "emitter shapes must have a positive size and an angle between 0 and 360, got {:?}",
+                shape
+            ),
+            SettingsError::FrequencyRange(range) => {
+                write!(f, "frequency_range must be within 0 and 1, got {:?}", range)
+            }
+            SettingsError::InvalidLayout(layout) => write!(
+                f,
+                "the radius of a polar layout must not be negative, got {:?}",
+                layout
+            ),
+            SettingsError::NegativeExposure(exposure) => write!(
+                f,
+                "tonemapping.exposure must not be negative, got {}",
+                exposure
+            ),
+            SettingsError::InvalidBlur(blur) => write!(
+                f,
+                "blur needs a positive radius, 1 to 8 iterations and a downsample of 1 to 16, got {:?}",
+                blur
+            ),
+            SettingsError::FeedbackZoom(zoom) => write!(
+                f,
+                "feedback.zoom must stay positive, even with feedback.audio.zoom, got {}",
+                zoom
+            ),
+            SettingsError::InvalidEffect(i) => {
+                write!(f, "effect {} has a parameter out of range", i)
+            }
+            SettingsError::CustomShader { path, reason } => {
+                write!(f, "couldn't compile shader {}: {}", path.display(), reason)
+            }
+        }
+    }
+}
+
+impl std::error::Error for SettingsError {}
@@ -0,0 +1,374 @@
+use super::{
+    render_target::{RenderTarget, RenderTargetFamily},
+    SettingsError,
+};
+use glam::Vec2;
+use std::{f32::consts::TAU, future::Future, ops::Range, time::Duration};
+
+// cells across and down the warp mesh, as in feedback.vert
+const GRID: u32 = 32;
+// the uniforms, padded to the 16 bytes uniform blocks are rounded up to
+const UNIFORMS_SIZE: u64 = 48;
+
+/// How the previous frame is moved as it fades into the next, building up into tunnels and swirls.
+///
+/// Everything applies once per frame, like `decay`, around the
+/// [`center`](Self::center) and in frame heights, so that rotations stay round.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct Feedback {
+    /// Above 1 grows the previous frame, flying into a tunnel; below 1 shrinks it.
+    pub zoom: f32,
+    /// Counterclockwise, in degrees.
+    pub rotation: f32,
+    pub translation: Vec2,
+    /// Center of the zoom and rotation, from (0, 0) at the bottom left to (1, 1) at the top right.
+    pub center: Vec2,
+    /// How far the warp mesh pushes the frame around.
+    pub warp: f32,
+    /// Number of warp waves across the frame.
+    pub warp_scale: f32,
+    /// Warp waves per second going through each point.
+    pub warp_speed: f32,
+    pub audio: FeedbackAudio,
+}
+
+/// How much louder sound adds to the feedback.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct FeedbackAudio {
+    /// The part of the spectrum whose level drives the feedback, from 0 (the lowest band) to 1
+    /// (the highest).
+    pub frequency_range: Range<f32>,
+    /// Added to [`Feedback::zoom`] at a level of 1, and proportionally less below it.
+    pub zoom: f32,
+    /// Added to [`Feedback::rotation`] at a level of 1.
+    pub rotation: f32,
+    /// Added to [`Feedback::translation`] at a level of 1.
+    pub translation: Vec2,
+    /// Added to [`Feedback::warp`] at a level of 1.
+    pub warp: f32,
+}
+
+impl Feedback {
+    /// Whether the feedback moves anything at all.
+    pub fn is_enabled(&self) -> bool {
+        let audio = &self.audio;
+
+        self.zoom != 1.0
+            || self.rotation != 0.0
+            || self.translation != Vec2::zero()
+            || self.warp != 0.0
+            || audio.zoom != 0.0
+            || audio.rotation != 0.0
+            || audio.translation != Vec2::zero()
+            || audio.warp != 0.0
+    }
+
+    pub fn validate(&self) -> Result<(), SettingsError> {
+        let floats = [
+            self.zoom,
+            self.rotation,
+            self.translation.x,
+            self.translation.y,
+            self.center.x,
+            self.center.y,
+            self.warp,
+            self.warp_scale,
+            self.warp_speed,
+            self.audio.frequency_range.start,
+            self.audio.frequency_range.end,
+            self.audio.zoom,
+            self.audio.rotation,
+            self.audio.translation.x,
+            self.audio.translation.y,
+            self.audio.warp,
+        ];
+
+        if !floats.iter().all(|v| v.is_finite()) {
+            return Err(SettingsError::NotFinite("feedback"));
+        }
+
+        // at every level
+        for &zoom in &[self.zoom, self.zoom + self.audio.zoom] {
+            if zoom <= 0.0 {
+                return Err(SettingsError::FeedbackZoom(zoom));
+            }
+        }
+
+        let range = &self.audio.frequency_range;
+
+        if range.start > range.end {
+            return Err(SettingsError::ReversedRange(
+                "feedback.audio.frequency_range",
+            ));
+        }
+
+        if !(0.0..=1.0).contains(&range.start) || !(0.0..=1.0).contains(&range.end) {
+            return Err(SettingsError::FrequencyRange(range.clone()));
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for Feedback {
+    /// Leaves the previous frame where it is.
+    fn default() -> Self {
+        Self {
+            zoom: 1.0,
+            rotation: 0.0,
+            translation: Vec2::zero(),
+            center: (0.5, 0.5).into(),
+            warp: 0.0,
+            warp_scale: 2.0,
+            warp_speed: 0.25,
+            audio: FeedbackAudio::default(),
+        }
+    }
+}
+
+impl FeedbackAudio {
+    /// Mean level of the bands within the frequency range, `bands` covering the whole spectrum.
+    pub fn level(&self, bands: &[f32]) -> f32 {
+        if bands.is_empty() {
+            return 0.0;
+        }
+
+        let n = bands.len() as f32;
+        let start = ((self.frequency_range.start * n) as usize).min(bands.len() - 1);
+        let end = ((self.frequency_range.end * n).ceil() as usize).clamp(start + 1, bands.len());
+
+        bands[start..end].iter().sum::<f32>() / (end - start) as f32
+    }
+}
+
+impl Default for FeedbackAudio {
+    fn default() -> Self {
+        Self {
+            frequency_range: 0.0..1.0,
+            zoom: 0.0,
+            rotation: 0.0,
+            translation: Vec2::zero(),
+            warp: 0.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Uniforms {
+    center: Vec2,
+    translation: Vec2,
+    zoom: f32,
+    rotation: f32,
+    warp: f32,
+    warp_scale: f32,
+    warp_phase: f32,
+    aspect: f32,
+}
+
+impl Uniforms {
+    fn raw(&self) -> [u8; UNIFORMS_SIZE as usize] {
+        bytemuck::cast([
+            self.center.x,
+            self.center.y,
+            self.translation.x,
+            self.translation.y,
+            self.zoom,
+            self.rotation,
+            self.warp,
+            self.warp_scale,
+            self.warp_phase,
+            self.aspect,
+            0.0,
+            0.0,
+        ])
+    }
+}
+
+pub struct FeedbackRenderer {
+    render_pipeline: wgpu::RenderPipeline,
+    bind_group: wgpu::BindGroup,
+    staging_belt: wgpu::util::StagingBelt,
+    uniform_buf: wgpu::Buffer,
+    time: Duration,
+    level: f32,
+    // width over height
+    aspect: f32,
+}
+
+impl FeedbackRenderer {
+    pub fn new(device: &wgpu::Device, family: &RenderTargetFamily) -> Self {
+        let vs_module =
+            device.create_shader_module(wgpu::include_spirv!("shaders/feedback.vert.spv"));
+        let fs_module =
+            device.create_shader_module(wgpu::include_spirv!("shaders/compositor.frag.spv"));
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: None,
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStage::VERTEX,
+                ty: wgpu::BindingType::UniformBuffer {
+                    dynamic: false,
+                    min_binding_size: wgpu::BufferSize::new(UNIFORMS_SIZE),
+                },
+                count: None,
+            }],
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&bind_group_layout, &family.bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let uniform_buf = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("uniform buffer"),
+            usage: wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST,
+            size: UNIFORMS_SIZE,
+            mapped_at_creation: false,
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout: &bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::Buffer(uniform_buf.slice(..)),
+            }],
+        });
+
+        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: None,
+            layout: Some(&pipeline_layout),
+            vertex_stage: wgpu::ProgrammableStageDescriptor {
+                module: &vs_module,
+                entry_point: "main",
+            },
+            fragment_stage: Some(wgpu::ProgrammableStageDescriptor {
+                module: &fs_module,
+                entry_point: "main",
+            }),
+            rasterization_state: Some(wgpu::RasterizationStateDescriptor {
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: wgpu::CullMode::Back,
+                ..Default::default()
+            }),
+            primitive_topology: wgpu::PrimitiveTopology::TriangleList,
+            // fades like `Compositor::render_transparent`
+            color_states: &[wgpu::ColorStateDescriptor {
+                format: family.format,
+                color_blend: wgpu::BlendDescriptor {
+                    src_factor: wgpu::BlendFactor::BlendColor,
+                    dst_factor: wgpu::BlendFactor::Zero,
+                    operation: wgpu::BlendOperation::Add,
+                },
+                alpha_blend: wgpu::BlendDescriptor {
+                    src_factor: wgpu::BlendFactor::BlendColor,
+                    dst_factor: wgpu::BlendFactor::Zero,
+                    operation: wgpu::BlendOperation::Add,
+                },
+                write_mask: wgpu::ColorWrite::ALL,
+            }],
+            depth_stencil_state: None,
+            vertex_state: wgpu::VertexStateDescriptor {
+                index_format: wgpu::IndexFormat::Uint16,
+                vertex_buffers: &[],
+            },
+            sample_count: 1,
+            sample_mask: !0,
+            alpha_to_coverage_enabled: false,
+        });
+
+        let staging_belt = wgpu::util::StagingBelt::new(0x100);
+
+        Self {
+            render_pipeline,
+            bind_group,
+            staging_belt,
+            uniform_buf,
+            time: Duration::default(),
+            level: 0.0,
+            aspect: 1.0,
+        }
+    }
+
+    /// Gets the upload buffers back for reuse, like `Compositor::recall`.
+    pub fn recall(&mut self) -> impl Future<Output = ()> + Send {
+        self.staging_belt.recall()
+    }
+
+    pub fn resize(&mut self, width: u32, height: u32) {
+        if height > 0 {
+            self.aspect = width as f32 / height as f32;
+        }
+    }
+
+    /// `freq_data` holds the level of each band, across the whole spectrum.
+    pub fn update(&mut self, delta: Duration, freq_data: &[f32], settings: &Feedback) {
+        self.time += delta;
+        self.level = settings.audio.level(freq_data);
+    }
+
+    /// Replaces `dest_view` with `source` moved by the feedback, and faded by `opacity`.
+    pub fn render(
+        &mut self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        source: &RenderTarget,
+        dest_view: &wgpu::TextureView,
+        opacity: f64,
+        settings: &Feedback,
+    ) {
+        let level = self.level;
+        let audio = &settings.audio;
+        let uniforms = Uniforms {
+            center: settings.center,
+            translation: settings.translation + audio.translation * level,
+            zoom: settings.zoom + audio.zoom * level,
+            rotation: (settings.rotation + audio.rotation * level).to_radians(),
+            warp: settings.warp + audio.warp * level,
+            warp_scale: settings.warp_scale,
+            warp_phase: self.time.as_secs_f32() * settings.warp_speed * TAU,
+            aspect: self.aspect,
+        };
+
+        self.staging_belt
+            .write_buffer(
+                encoder,
+                &self.uniform_buf,
+                0,
+                wgpu::BufferSize::new(UNIFORMS_SIZE).unwrap(),
+                device,
+            )
+            .copy_from_slice(&uniforms.raw());
+
+        self.staging_belt.finish();
+
+        let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            color_attachments: &[wgpu::RenderPassColorAttachmentDescriptor {
+                attachment: dest_view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: true,
+                },
+            }],
+            depth_stencil_attachment: None,
+        });
+
+        rpass.set_pipeline(&self.render_pipeline);
+        rpass.set_blend_color(wgpu::Color {
+            r: opacity,
+            g: opacity,
+            b: opacity,
+            a: opacity,
+        });
+        rpass.set_bind_group(0, &self.bind_group, &[]);
+        rpass.set_bind_group(1, &source.bind_group, &[]);
+        rpass.draw(0..GRID * GRID * 6, 0..1);
+    }
+}
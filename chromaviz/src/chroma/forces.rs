@@ -0,0 +1,195 @@
+use super::{Layout, SettingsError};
+use glam::{Vec2, Vec3};
+
+/// Longest integration step, in seconds. Longer frames are split into several steps.
+pub const STEP: f32 = 1.0 / 240.0;
+/// Particles that never land or leave the frame die after this many seconds.
+pub const MAX_LIFETIME: f32 = 10.0;
+
+/// Forces besides gravity. With all of them off, particles follow exact ballistic arcs.
+#[derive(Debug, Clone, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct Forces {
+    /// Deceleration proportional to the speed, per second.
+    pub linear_drag: f32,
+    /// Deceleration proportional to the square of the speed.
+    pub quadratic_drag: f32,
+    /// Constant acceleration, usually sideways.
+    pub wind: Vec2,
+    pub turbulence: Turbulence,
+}
+
+/// Swirls from a curl noise field, which moves particles around without bunching them up.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct Turbulence {
+    /// Acceleration of the strongest swirls. 0 turns turbulence off.
+    pub strength: f32,
+    /// Roughly how many swirls fit across the frame.
+    pub scale: f32,
+    /// How fast the swirls change.
+    pub speed: f32,
+    /// How much the level of a particle's band scales the strength, from 0 (not at all) to 1
+    /// (silent bands don't swirl at all).
+    pub modulation: f32,
+}
+
+impl Default for Turbulence {
+    fn default() -> Self {
+        Self {
+            strength: 0.0,
+            scale: 4.0,
+            speed: 0.5,
+            modulation: 0.0,
+        }
+    }
+}
+
+impl Forces {
+    /// Whether particles need to be integrated step by step.
+    pub fn is_enabled(&self) -> bool {
+        self.linear_drag != 0.0
+            || self.quadratic_drag != 0.0
+            || self.wind != Vec2::zero()
+            || self.turbulence.strength != 0.0
+    }
+
+    pub fn validate(&self) -> Result<(), SettingsError> {
+        let floats = [
+            ("forces.linear_drag", self.linear_drag),
+            ("forces.quadratic_drag", self.quadratic_drag),
+            ("forces.wind", self.wind.x),
+            ("forces.wind", self.wind.y),
+            ("forces.turbulence.strength", self.turbulence.strength),
+            ("forces.turbulence.scale", self.turbulence.scale),
+            ("forces.turbulence.speed", self.turbulence.speed),
+            ("forces.turbulence.modulation", self.turbulence.modulation),
+        ];
+
+        if let Some(&(field, _)) = floats.iter().find(|(_, v)| !v.is_finite()) {
+            return Err(SettingsError::NotFinite(field));
+        }
+
+        let magnitudes = [
+            ("forces.linear_drag", self.linear_drag),
+            ("forces.quadratic_drag", self.quadratic_drag),
+            ("forces.turbulence.strength", self.turbulence.strength),
+            ("forces.turbulence.scale", self.turbulence.scale),
+        ];
+
+        if let Some(&(field, _)) = magnitudes.iter().find(|(_, v)| *v < 0.0) {
+            return Err(SettingsError::NegativeForce(field));
+        }
+
+        if !(0.0..=1.0).contains(&self.turbulence.modulation) {
+            return Err(SettingsError::TurbulenceModulation(
+                self.turbulence.modulation,
+            ));
+        }
+
+        Ok(())
+    }
+
+    // acceleration besides gravity, `level` being the level of the particle's band
+    fn acceleration(&self, pos: Vec2, vel: Vec2, time: f32, level: f32) -> Vec2 {
+        let mut acc = self.wind - vel * (self.linear_drag + self.quadratic_drag * vel.length());
+        let turbulence = &self.turbulence;
+
+        if turbulence.strength != 0.0 {
+            let modulation = 1.0 - turbulence.modulation + turbulence.modulation * level;
+            let field = (pos * turbulence.scale).extend(time * turbulence.speed);
+
+            acc += curl(field) * turbulence.strength * modulation;
+        }
+
+        acc
+    }
+
+    /// Seconds until a particle lands or leaves the frame, at most [`MAX_LIFETIME`].
+    ///
+    /// Only gravity and wind are constant enough to be accounted for. Drag and turbulence can take
+    /// the particle out sooner, where the update kills it, or keep it in longer than this.
+    pub fn lifetime(&self, g: Vec2, layout: &Layout, pos: Vec2, vel: Vec2) -> f32 {
+        layout
+            .exit_time(pos, vel, g + self.wind)
+            .map_or(MAX_LIFETIME, |t| t.min(MAX_LIFETIME))
+    }
+
+    /// Advances a particle by `dt` seconds, in steps of at most [`STEP`].
+    pub fn integrate(
+        &self,
+        g: Vec2,
+        pos: &mut Vec2,
+        vel: &mut Vec2,
+        dt: f32,
+        time: f32,
+        level: f32,
+    ) {
+        let steps = (dt / STEP).ceil().max(1.0);
+        let h = dt / steps;
+
+        for i in 0..steps as u32 {
+            let acc = g + self.acceleration(*pos, *vel, time + i as f32 * h, level);
+
+            // semi-implicit Euler, which is stable enough for drag
+            *vel += acc * h;
+            *pos += *vel * h;
+        }
+    }
+}
+
+// PCG hash, the same as in the compute shaders
+fn hash(x: u32) -> u32 {
+    let state = x.wrapping_mul(747796405).wrapping_add(2891336453);
+    let word = ((state >> ((state >> 28) + 4)) ^ state).wrapping_mul(277803737);
+
+    (word >> 22) ^ word
+}
+
+// random value in [-1, 1] at a lattice point
+fn lattice(x: i32, y: i32, z: i32) -> f32 {
+    let h = hash(x as u32 ^ hash(y as u32 ^ hash(z as u32)));
+
+    (h >> 8) as f32 / 8388608.0 - 1.0
+}
+
+// x and y derivatives of 3D value noise, see https://iquilezles.org/articles/morenoise
+fn noise_gradient(p: Vec3) -> Vec2 {
+    let i = p.floor();
+    let f = p - i;
+    let (x, y, z) = (i.x as i32, i.y as i32, i.z as i32);
+
+    // quintic fade, whose first and second derivatives vanish at the lattice
+    let u = f * f * f * (f * (f * 6.0 - Vec3::splat(15.0)) + Vec3::splat(10.0));
+    let du = 30.0 * f * f * (f * (f - Vec3::splat(2.0)) + Vec3::splat(1.0));
+
+    let a = lattice(x, y, z);
+    let b = lattice(x + 1, y, z);
+    let c = lattice(x, y + 1, z);
+    let d = lattice(x + 1, y + 1, z);
+    let e = lattice(x, y, z + 1);
+    let f = lattice(x + 1, y, z + 1);
+    let g = lattice(x, y + 1, z + 1);
+    let h = lattice(x + 1, y + 1, z + 1);
+
+    let k1 = b - a;
+    let k2 = c - a;
+    let k4 = a - b - c + d;
+    let k5 = a - c - e + g;
+    let k6 = a - b - e + f;
+    let k7 = -a + b + c - d + e - f - g + h;
+
+    Vec2::new(
+        du.x * (k1 + k4 * u.y + k6 * u.z + k7 * u.y * u.z),
+        du.y * (k2 + k5 * u.z + k4 * u.x + k7 * u.z * u.x),
+    )
+}
+
+// divergence free field, rotated 90° from the gradient of the noise
+fn curl(p: Vec3) -> Vec2 {
+    let gradient = noise_gradient(p);
+
+    Vec2::new(gradient.y, -gradient.x)
+}
@@ -0,0 +1,549 @@
+use super::{
+    emitter::{Emitter, EmitterShape},
+    layout::Layout,
+    palette::PaletteKey,
+    particle::{OverflowPolicy, INSTANCE_SIZE},
+};
+use futures::executor::block_on;
+use std::{convert::TryInto, time::Duration};
+
+const WORKGROUP_SIZE: u32 = 64;
+// std430 size of `Particle` in the shaders
+const PARTICLE_SIZE: u64 = 64;
+// the shaders' dropped particle counter
+const STATS_SIZE: u64 = 4;
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+struct Params {
+    gravity: [f32; 2],
+    delta: f32,
+    period: f32,
+    capacity: u32,
+    head: u32,
+    spawn_count: u32,
+    live_count: u32,
+    frequencies: u32,
+    frequencies_spread: f32,
+    angular_spread: f32,
+    velocity_spread: f32,
+    size_range: [f32; 2],
+    seed: u32,
+    bands: u32,
+    overflow: u32,
+    palette_key: u32,
+    sprites: u32,
+    _padding: u32,
+    rotation_range: [f32; 2],
+    spin_range: [f32; 2],
+    time: f32,
+    forces: u32,
+    linear_drag: f32,
+    quadratic_drag: f32,
+    wind: [f32; 2],
+    turbulence_strength: f32,
+    turbulence_scale: f32,
+    turbulence_speed: f32,
+    turbulence_modulation: f32,
+    position: [f32; 2],
+    orientation: f32,
+    shape: u32,
+    shape_size: f32,
+    shape_angle: f32,
+    frequency_range: [f32; 2],
+    mirrored: u32,
+    layout: u32,
+    polar_center: [f32; 2],
+    polar_radius: f32,
+    aspect: f32,
+}
+
+unsafe impl bytemuck::Zeroable for Params {}
+unsafe impl bytemuck::Pod for Params {}
+
+/// A simulation step recorded on the CPU, to be run on the GPU with the next render.
+#[derive(Debug, Clone)]
+pub struct Step {
+    pub delta: Duration,
+    pub period: Duration,
+    pub spawn_count: u32,
+    pub seed: u32,
+    pub bands: Vec<f32>,
+}
+
+/// Particles spawned, aged and killed by compute shaders.
+///
+/// Particles live in a ring buffer. When a new particle would land on a slot whose particle is still
+/// alive, the overflow policy decides which of the two is dropped.
+pub struct GpuParticles {
+    spawn_pipeline: wgpu::ComputePipeline,
+    update_pipeline: wgpu::ComputePipeline,
+    bind_group_layout: wgpu::BindGroupLayout,
+    bind_group: wgpu::BindGroup,
+    params_buf: wgpu::Buffer,
+    particle_buf: wgpu::Buffer,
+    instance_buf: wgpu::Buffer,
+    bands_buf: wgpu::Buffer,
+    stats_buf: wgpu::Buffer,
+    stats_readback_buf: wgpu::Buffer,
+    bands_capacity: usize,
+    capacity: u32,
+    // particles that didn't fit in a single step, which the shaders never see
+    clamped: u64,
+    // next slot to spawn into, and number of slots that were ever used
+    head: u32,
+    live: u32,
+    // simulated so far, which animates the turbulence
+    time: Duration,
+}
+
+impl GpuParticles {
+    pub fn new(device: &wgpu::Device, capacity: u32) -> Self {
+        let spawn_module =
+            device.create_shader_module(wgpu::include_spirv!("shaders/particle_spawn.comp.spv"));
+        let update_module =
+            device.create_shader_module(wgpu::include_spirv!("shaders/particle_update.comp.spv"));
+
+        let storage = |binding, readonly, min_size| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStage::COMPUTE,
+            ty: wgpu::BindingType::StorageBuffer {
+                dynamic: false,
+                min_binding_size: wgpu::BufferSize::new(min_size),
+                readonly,
+            },
+            count: None,
+        };
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: None,
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStage::COMPUTE,
+                    ty: wgpu::BindingType::UniformBuffer {
+                        dynamic: false,
+                        min_binding_size: wgpu::BufferSize::new(
+                            std::mem::size_of::<Params>() as u64
+                        ),
+                    },
+                    count: None,
+                },
+                storage(1, false, PARTICLE_SIZE),
+                storage(2, false, INSTANCE_SIZE),
+                storage(3, true, std::mem::size_of::<f32>() as u64),
+                storage(4, false, STATS_SIZE),
+            ],
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = |module| {
+            device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: None,
+                layout: Some(&pipeline_layout),
+                compute_stage: wgpu::ProgrammableStageDescriptor {
+                    module,
+                    entry_point: "main",
+                },
+            })
+        };
+
+        let spawn_pipeline = pipeline(&spawn_module);
+        let update_pipeline = pipeline(&update_module);
+
+        let params_buf = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("particle simulation parameters"),
+            usage: wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST,
+            size: std::mem::size_of::<Params>() as u64,
+            mapped_at_creation: false,
+        });
+
+        let particle_buf = create_particle_buffer(device, capacity);
+        let instance_buf = create_instance_buffer(device, capacity);
+
+        let stats_buf = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("particle stats"),
+            usage: wgpu::BufferUsage::STORAGE | wgpu::BufferUsage::COPY_SRC,
+            size: STATS_SIZE,
+            mapped_at_creation: true,
+        });
+
+        zero(&stats_buf);
+
+        let stats_readback_buf = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("particle stats readback"),
+            usage: wgpu::BufferUsage::MAP_READ | wgpu::BufferUsage::COPY_DST,
+            size: STATS_SIZE,
+            mapped_at_creation: false,
+        });
+
+        let bands_capacity = 32;
+        let bands_buf = create_bands_buffer(device, bands_capacity);
+        let bind_group = create_bind_group(
+            device,
+            &bind_group_layout,
+            &params_buf,
+            &particle_buf,
+            &instance_buf,
+            &bands_buf,
+            &stats_buf,
+        );
+
+        Self {
+            spawn_pipeline,
+            update_pipeline,
+            bind_group_layout,
+            bind_group,
+            params_buf,
+            particle_buf,
+            instance_buf,
+            bands_buf,
+            stats_buf,
+            stats_readback_buf,
+            bands_capacity,
+            capacity,
+            clamped: 0,
+            head: 0,
+            live: 0,
+            time: Duration::from_secs(0),
+        }
+    }
+
+    /// The instance buffer to draw from, and how many instances it holds.
+    pub fn instances(&self) -> (&wgpu::Buffer, u32) {
+        (&self.instance_buf, self.live)
+    }
+
+    pub fn capacity(&self) -> u32 {
+        self.capacity
+    }
+
+    /// Grows the particle buffers, keeping the particles they hold.
+    pub fn grow(
+        &mut self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        capacity: u32,
+    ) {
+        if capacity <= self.capacity {
+            return;
+        }
+
+        let particle_buf = create_particle_buffer(device, capacity);
+        let instance_buf = create_instance_buffer(device, capacity);
+
+        encoder.copy_buffer_to_buffer(
+            &self.particle_buf,
+            0,
+            &particle_buf,
+            0,
+            self.capacity as u64 * PARTICLE_SIZE,
+        );
+        encoder.copy_buffer_to_buffer(
+            &self.instance_buf,
+            0,
+            &instance_buf,
+            0,
+            self.capacity as u64 * INSTANCE_SIZE,
+        );
+
+        self.particle_buf = particle_buf;
+        self.instance_buf = instance_buf;
+        self.capacity = capacity;
+        self.bind_group = create_bind_group(
+            device,
+            &self.bind_group_layout,
+            &self.params_buf,
+            &self.particle_buf,
+            &self.instance_buf,
+            &self.bands_buf,
+            &self.stats_buf,
+        );
+    }
+
+    /// Copies the dropped particle counter where [`dropped`](Self::dropped) can read it.
+    pub fn copy_stats(&self, encoder: &mut wgpu::CommandEncoder) {
+        encoder.copy_buffer_to_buffer(&self.stats_buf, 0, &self.stats_readback_buf, 0, STATS_SIZE);
+    }
+
+    /// Number of particles dropped as of the last submitted [`copy_stats`](Self::copy_stats).
+    ///
+    /// Blocks until the GPU is done with the submitted work.
+    pub fn dropped(&self, device: &wgpu::Device) -> u64 {
+        let slice = self.stats_readback_buf.slice(..);
+        let mapping = slice.map_async(wgpu::MapMode::Read);
+
+        device.poll(wgpu::Maintain::Wait);
+
+        let counted = match block_on(mapping) {
+            Ok(()) => {
+                let counted = u32::from_ne_bytes(slice.get_mapped_range()[..4].try_into().unwrap());
+
+                self.stats_readback_buf.unmap();
+                counted
+            }
+            Err(_) => 0,
+        };
+
+        self.clamped + counted as u64
+    }
+
+    /// Grows the bands buffer ahead of the steps that need it.
+    ///
+    /// Must be called before recording any step of the frame.
+    pub fn reserve_bands(&mut self, device: &wgpu::Device, bands: usize) {
+        if bands > self.bands_capacity {
+            self.bands_capacity = bands.next_power_of_two();
+            self.bands_buf = create_bands_buffer(device, self.bands_capacity);
+            self.bind_group = create_bind_group(
+                device,
+                &self.bind_group_layout,
+                &self.params_buf,
+                &self.particle_buf,
+                &self.instance_buf,
+                &self.bands_buf,
+                &self.stats_buf,
+            );
+        }
+    }
+
+    pub fn simulate(
+        &mut self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        staging_belt: &mut wgpu::util::StagingBelt,
+        step: &Step,
+        emitter: &Emitter,
+        aspect: f32,
+    ) {
+        let settings = &emitter.particles;
+        // spawning more than the capacity at once would make invocations write the same slot
+        let spawn_count = if step.bands.is_empty() {
+            0
+        } else {
+            step.spawn_count.min(self.capacity)
+        };
+        let live = (self.live + spawn_count).min(self.capacity);
+
+        if !step.bands.is_empty() {
+            self.clamped += (step.spawn_count - spawn_count) as u64;
+        }
+
+        let params = Params {
+            gravity: [settings.gravity.x, settings.gravity.y],
+            delta: step.delta.as_secs_f32(),
+            period: step.period.as_secs_f32(),
+            capacity: self.capacity,
+            head: self.head,
+            spawn_count,
+            live_count: live,
+            frequencies: settings.frequencies as u32,
+            frequencies_spread: settings.frequencies_spread,
+            angular_spread: settings.angular_spread,
+            velocity_spread: settings.velocity_spread,
+            size_range: [settings.size_range.start, settings.size_range.end],
+            seed: step.seed,
+            bands: step.bands.len() as u32,
+            overflow: match settings.overflow {
+                OverflowPolicy::RecycleOldest => 1,
+                OverflowPolicy::DropNewest | OverflowPolicy::ScaleEmission => 0,
+            },
+            palette_key: match settings.palette.key {
+                PaletteKey::Frequency => 0,
+                PaletteKey::Age => 1,
+                PaletteKey::Velocity => 2,
+                PaletteKey::Energy => 3,
+            },
+            sprites: settings.sprites.len() as u32,
+            _padding: 0,
+            rotation_range: [
+                settings.rotation_range.start.to_radians(),
+                settings.rotation_range.end.to_radians(),
+            ],
+            spin_range: [
+                settings.spin_range.start.to_radians(),
+                settings.spin_range.end.to_radians(),
+            ],
+            time: self.time.as_secs_f32(),
+            forces: settings.forces.is_enabled() as u32,
+            linear_drag: settings.forces.linear_drag,
+            quadratic_drag: settings.forces.quadratic_drag,
+            wind: [settings.forces.wind.x, settings.forces.wind.y],
+            turbulence_strength: settings.forces.turbulence.strength,
+            turbulence_scale: settings.forces.turbulence.scale,
+            turbulence_speed: settings.forces.turbulence.speed,
+            turbulence_modulation: settings.forces.turbulence.modulation,
+            position: [emitter.position.x, emitter.position.y],
+            orientation: emitter.orientation.to_radians(),
+            // lines and arcs, which points and circles are special cases of
+            shape: match emitter.shape {
+                EmitterShape::Line { .. } | EmitterShape::Point => 0,
+                EmitterShape::Circle { .. } | EmitterShape::Arc { .. } => 1,
+            },
+            shape_size: match emitter.shape {
+                EmitterShape::Line { length } => length,
+                EmitterShape::Point => 0.0,
+                EmitterShape::Circle { radius } | EmitterShape::Arc { radius, .. } => radius,
+            },
+            shape_angle: match emitter.shape {
+                EmitterShape::Circle { .. } => std::f32::consts::TAU,
+                EmitterShape::Arc { angle, .. } => angle.to_radians(),
+                EmitterShape::Line { .. } | EmitterShape::Point => 0.0,
+            },
+            frequency_range: [emitter.frequency_range.start, emitter.frequency_range.end],
+            mirrored: emitter.mirrored as u32,
+            layout: match emitter.layout {
+                Layout::Cartesian => 0,
+                Layout::Polar { .. } => 1,
+            },
+            polar_center: match emitter.layout {
+                Layout::Polar { center, .. } => [center.x, center.y],
+                Layout::Cartesian => [0.0; 2],
+            },
+            polar_radius: match emitter.layout {
+                Layout::Polar { radius, .. } => radius,
+                Layout::Cartesian => 0.0,
+            },
+            aspect,
+        };
+
+        staging_belt
+            .write_buffer(
+                encoder,
+                &self.params_buf,
+                0,
+                wgpu::BufferSize::new(std::mem::size_of::<Params>() as u64).unwrap(),
+                device,
+            )
+            .copy_from_slice(bytemuck::bytes_of(&params));
+
+        // the update reads them too, for the turbulence
+        if !step.bands.is_empty() {
+            staging_belt
+                .write_buffer(
+                    encoder,
+                    &self.bands_buf,
+                    0,
+                    wgpu::BufferSize::new(
+                        (step.bands.len() * std::mem::size_of::<f32>()) as wgpu::BufferAddress,
+                    )
+                    .unwrap(),
+                    device,
+                )
+                .copy_from_slice(bytemuck::cast_slice(&step.bands));
+        }
+
+        if spawn_count > 0 {
+            let mut cpass = encoder.begin_compute_pass();
+
+            cpass.set_pipeline(&self.spawn_pipeline);
+            cpass.set_bind_group(0, &self.bind_group, &[]);
+            cpass.dispatch(workgroups(spawn_count), 1, 1);
+        }
+
+        // separate passes, so that the update sees the spawned particles
+        if live > 0 {
+            let mut cpass = encoder.begin_compute_pass();
+
+            cpass.set_pipeline(&self.update_pipeline);
+            cpass.set_bind_group(0, &self.bind_group, &[]);
+            cpass.dispatch(workgroups(live), 1, 1);
+        }
+
+        self.head = (self.head + spawn_count) % self.capacity;
+        self.live = live;
+        self.time += step.delta;
+    }
+}
+
+fn workgroups(invocations: u32) -> u32 {
+    (invocations + WORKGROUP_SIZE - 1) / WORKGROUP_SIZE
+}
+
+// every particle must start out dead, so clear the buffer explicitly
+fn create_particle_buffer(device: &wgpu::Device, capacity: u32) -> wgpu::Buffer {
+    let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("particle state"),
+        usage: wgpu::BufferUsage::STORAGE
+            | wgpu::BufferUsage::COPY_SRC
+            | wgpu::BufferUsage::COPY_DST,
+        size: capacity as u64 * PARTICLE_SIZE,
+        mapped_at_creation: true,
+    });
+
+    zero(&buffer);
+    buffer
+}
+
+fn create_instance_buffer(device: &wgpu::Device, capacity: u32) -> wgpu::Buffer {
+    device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("particle instances"),
+        usage: wgpu::BufferUsage::STORAGE
+            | wgpu::BufferUsage::VERTEX
+            | wgpu::BufferUsage::COPY_SRC
+            | wgpu::BufferUsage::COPY_DST,
+        size: capacity as u64 * INSTANCE_SIZE,
+        mapped_at_creation: false,
+    })
+}
+
+// `buffer` must be mapped
+fn zero(buffer: &wgpu::Buffer) {
+    buffer
+        .slice(..)
+        .get_mapped_range_mut()
+        .iter_mut()
+        .for_each(|b| *b = 0);
+    buffer.unmap();
+}
+
+fn create_bands_buffer(device: &wgpu::Device, bands: usize) -> wgpu::Buffer {
+    device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("particle bands"),
+        usage: wgpu::BufferUsage::STORAGE | wgpu::BufferUsage::COPY_DST,
+        size: (bands * std::mem::size_of::<f32>()) as wgpu::BufferAddress,
+        mapped_at_creation: false,
+    })
+}
+
+fn create_bind_group(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    params_buf: &wgpu::Buffer,
+    particle_buf: &wgpu::Buffer,
+    instance_buf: &wgpu::Buffer,
+    bands_buf: &wgpu::Buffer,
+    stats_buf: &wgpu::Buffer,
+) -> wgpu::BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: None,
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::Buffer(params_buf.slice(..)),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::Buffer(particle_buf.slice(..)),
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: wgpu::BindingResource::Buffer(instance_buf.slice(..)),
+            },
+            wgpu::BindGroupEntry {
+                binding: 3,
+                resource: wgpu::BindingResource::Buffer(bands_buf.slice(..)),
+            },
+            wgpu::BindGroupEntry {
+                binding: 4,
+                resource: wgpu::BindingResource::Buffer(stats_buf.slice(..)),
+            },
+        ],
+    })
+}
@@ -0,0 +1,118 @@
+use super::SettingsError;
+use glam::Vec2;
+use std::f32::consts::TAU;
+
+// how far past the edges of the frame a particle still counts as on screen, as particles are
+// emitted a little past them and their sprites have a size
+const MARGIN: f32 = 0.05;
+
+/// How an emitter's particles are laid out in the frame.
+///
+/// Particles are always simulated as a fountain, across x and up y with gravity pulling down. The
+/// layout then maps them to the frame.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum Layout {
+    /// Straight to the frame, from (0, 0) at the bottom left to (1, 1) at the top right.
+    Cartesian,
+    /// Wrapped around a circle: x goes once around it, from the bottom up the left side, and y is
+    /// the distance out from its edge, so particles launch outwards and fall back towards the
+    /// center. Distances are in frame heights, so the circle stays round whatever the aspect ratio.
+    Polar { center: Vec2, radius: f32 },
+}
+
+impl Default for Layout {
+    fn default() -> Self {
+        Layout::Cartesian
+    }
+}
+
+impl Layout {
+    pub fn validate(&self) -> Result<(), SettingsError> {
+        if let Layout::Polar { center, radius } = *self {
+            if ![center.x, center.y, radius].iter().all(|v| v.is_finite()) {
+                return Err(SettingsError::NotFinite("layout"));
+            }
+
+            if radius < 0.0 {
+                return Err(SettingsError::InvalidLayout(self.clone()));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Where a simulated position ends up in the frame, `aspect` being its height over its width.
+    pub fn to_frame(&self, pos: Vec2, aspect: f32) -> Vec2 {
+        match *self {
+            Layout::Cartesian => pos,
+            Layout::Polar { center, radius } => {
+                // counterclockwise from the top, like `EmitterShape::Circle`
+                let a = (0.5 - pos.x) * TAU;
+                let distance = radius + pos.y;
+
+                center + Vec2::new(-a.sin() * distance * aspect, a.cos() * distance)
+            }
+        }
+    }
+
+    /// Whether a simulated particle hasn't landed or left through the sides of the frame. Those past
+    /// the top are still coming back, as gravity always pulls down.
+    pub fn in_frame(&self, pos: Vec2, vel: Vec2) -> bool {
+        let landed = pos.y < 0.0 && vel.y < 0.0;
+
+        match self {
+            Layout::Cartesian => !landed && (-MARGIN..=1.0 + MARGIN).contains(&pos.x),
+            // going around never leaves the frame
+            Layout::Polar { .. } => !landed,
+        }
+    }
+
+    /// How long until a particle under a constant acceleration stops being
+    /// [in the frame](Self::in_frame), if it ever does.
+    pub fn exit_time(&self, pos: Vec2, vel: Vec2, acc: Vec2) -> Option<f32> {
+        let landing = crossing(pos.y, vel.y, acc.y, 0.0, -1.0);
+        let sides = match self {
+            Layout::Cartesian => [
+                crossing(pos.x, vel.x, acc.x, -MARGIN, -1.0),
+                crossing(pos.x, vel.x, acc.x, 1.0 + MARGIN, 1.0),
+            ],
+            Layout::Polar { .. } => [None, None],
+        };
+
+        std::iter::once(landing)
+            .chain(sides)
+            .flatten()
+            .fold(None, |first, t| {
+                Some(first.map_or(t, |first: f32| first.min(t)))
+            })
+    }
+}
+
+// first time from now that `p + v t + a t² / 2` crosses `bound` towards the sign of `out`
+fn crossing(p: f32, v: f32, a: f32, bound: f32, out: f32) -> Option<f32> {
+    let c = p - bound;
+    let roots = if a == 0.0 {
+        // never when `v` is 0 either, as the root isn't finite
+        [-c / v, f32::NAN]
+    } else {
+        let discriminant = v * v - 2.0 * a * c;
+
+        if discriminant < 0.0 {
+            return None;
+        }
+
+        let s = discriminant.sqrt();
+
+        [(-v - s) / a, (-v + s) / a]
+    };
+
+    roots
+        .iter()
+        .copied()
+        .filter(|&t| t > 0.0 && t.is_finite() && (v + a * t) * out > 0.0)
+        .fold(None, |first, t| {
+            Some(first.map_or(t, |first: f32| first.min(t)))
+        })
+}
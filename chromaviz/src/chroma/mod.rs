@@ -1,62 +1,284 @@
 mod blur;
 mod compositor;
+mod custom_shader;
+mod effects;
+mod emitter;
+mod error;
+mod feedback;
+mod forces;
+mod gpu_particles;
+mod layout;
+mod palette;
 mod particle;
 mod render_target;
+mod sprite;
+mod tonemapping;
 
 use crate::Renderer;
-use blur::{BlurDirection, BlurRenderer};
+use blur::BlurRenderer;
+pub use blur::{Blur, BlurKernel};
 use compositor::Compositor;
+use custom_shader::CustomShaderRenderer;
+pub use custom_shader::{CustomShader, PRELUDE};
+use effects::EffectRenderer;
+pub use effects::{
+    ChromaticAberration, ColorGrading, Effect, Grain, Kaleidoscope, PostEffect, Scanlines, Vignette,
+};
+pub use emitter::{Emitter, EmitterShape};
+pub use error::SettingsError;
+use feedback::FeedbackRenderer;
+pub use feedback::{Feedback, FeedbackAudio};
+pub use forces::{Forces, Turbulence};
+use futures::{
+    future::BoxFuture,
+    stream::{FuturesUnordered, StreamExt},
+    task::noop_waker_ref,
+    FutureExt,
+};
+pub use layout::Layout;
+pub use palette::{ColorStop, Palette, PaletteKey};
 use particle::ParticleRenderer;
-pub use particle::ParticleSettings;
-use render_target::{RenderTarget, RenderTargetFamily};
-use std::time::Duration;
+pub use particle::{OverflowPolicy, Particle, ParticleBackend, ParticleSettings, ParticleSystem};
+use render_target::{RenderTarget, RenderTargetFamily, HDR_FORMAT};
+pub use sprite::Sprite;
+use sprite::{AtlasImage, SpriteAtlas};
+use std::{
+    task::{Context, Poll},
+    time::Duration,
+};
+pub use tonemapping::{Tonemap, Tonemapping};
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 pub struct ChromaSettings {
-    pub particles: ParticleSettings,
+    /// Drawn in order, later emitters on top.
+    pub emitters: Vec<Emitter>,
     pub decay: f64,
+    pub blur: Blur,
+    pub feedback: Feedback,
+    /// Applied in order to what is drawn, without feeding back into the next frame.
+    pub effects: Vec<PostEffect>,
+    /// Drawn in order after the effects.
+    pub shaders: Vec<CustomShader>,
+    pub tonemapping: Tonemapping,
+}
+
+impl ChromaSettings {
+    pub fn validate(&self) -> Result<(), SettingsError> {
+        if !self.decay.is_finite() {
+            return Err(SettingsError::NotFinite("decay"));
+        }
+
+        if !(0.0..=1.0).contains(&self.decay) {
+            return Err(SettingsError::DecayOutOfRange(self.decay));
+        }
+
+        if self.emitters.is_empty() {
+            return Err(SettingsError::NoEmitters);
+        }
+
+        self.blur.validate()?;
+        self.feedback.validate()?;
+        effects::validate(&self.effects)?;
+        self.tonemapping.validate()?;
+        self.emitters.iter().try_for_each(Emitter::validate)
+    }
+
+    /// Number of bands to analyze the spectrum into, enough for the emitter with the most
+    /// frequencies.
+    pub fn bands(&self) -> usize {
+        self.emitters
+            .iter()
+            .map(|emitter| emitter.particles.frequencies as usize)
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+impl Default for ChromaSettings {
+    fn default() -> Self {
+        Self {
+            emitters: vec![Emitter::default()],
+            decay: 0.95,
+            blur: Blur::default(),
+            feedback: Feedback::default(),
+            effects: Vec::new(),
+            shaders: Vec::new(),
+            tonemapping: Tonemapping::default(),
+        }
+    }
 }
 
 pub struct Chroma {
-    pub settings: ChromaSettings,
+    settings: ChromaSettings,
     render_target_family: RenderTargetFamily,
-    particle_renderer: ParticleRenderer,
+    // one per emitter, those of new emitters being created by the next render
+    particle_renderers: Vec<ParticleRenderer>,
+    // loaded by `set_settings` for each emitter, for the next render to upload
+    pending_sprites: Vec<Option<AtlasImage>>,
+    size: (u32, u32),
     blur_renderer: BlurRenderer,
+    feedback_renderer: FeedbackRenderer,
+    effect_renderer: EffectRenderer,
+    custom_shader_renderer: CustomShaderRenderer,
     compositor: Compositor,
-    low_res_targets: (RenderTarget, RenderTarget),
     accumulator: RenderTarget,
+    // upload buffers of the renderers, on their way back to be reused
+    recalls: FuturesUnordered<BoxFuture<'static, ()>>,
 }
 
 impl Chroma {
+    /// `format` is that of the views to [`render`](Renderer::render) to. Everything in between is
+    /// rendered in floating point, and [tonemapped](ChromaSettings::tonemapping) to it at the end.
     pub fn new(
         device: &wgpu::Device,
         width: u32,
         height: u32,
         format: wgpu::TextureFormat,
         settings: ChromaSettings,
-    ) -> Self {
-        let render_target_family = RenderTargetFamily::new(device, format);
-        let particle_renderer = ParticleRenderer::new(device, &render_target_family);
-        let blur_renderer = BlurRenderer::new(device, &render_target_family);
-        let compositor = Compositor::new(device, &render_target_family);
+    ) -> Result<Self, SettingsError> {
+        settings.validate()?;
 
-        Self {
-            low_res_targets: (
-                render_target_family.create_target(device, width / 2, height / 2),
-                render_target_family.create_target(device, width / 2, height / 2),
-            ),
+        let render_target_family = RenderTargetFamily::new(device, HDR_FORMAT);
+        let particle_renderers = settings
+            .emitters
+            .iter()
+            .map(|emitter| {
+                let image = AtlasImage::new(&emitter.particles.sprites)?;
+                let atlas = SpriteAtlas::new(device, &render_target_family, image);
+
+                Ok(ParticleRenderer::new(
+                    device,
+                    &render_target_family,
+                    &emitter.particles,
+                    atlas,
+                ))
+            })
+            .collect::<Result<_, SettingsError>>()?;
+        let blur_renderer = BlurRenderer::new(device, &render_target_family, width, height);
+        let mut feedback_renderer = FeedbackRenderer::new(device, &render_target_family);
+        let effect_renderer = EffectRenderer::new(device, &render_target_family, width, height);
+        let mut custom_shader_renderer =
+            CustomShaderRenderer::new(device, &render_target_family, width, height);
+        let compositor = Compositor::new(device, &render_target_family, format);
+
+        feedback_renderer.resize(width, height);
+        custom_shader_renderer.set_shaders(custom_shader::compile_all(&settings.shaders)?);
+
+        Ok(Self {
             accumulator: render_target_family.create_target(device, width, height),
             settings,
             render_target_family,
-            particle_renderer,
+            particle_renderers,
+            pending_sprites: Vec::new(),
+            size: (width, height),
             blur_renderer,
+            feedback_renderer,
+            effect_renderer,
+            custom_shader_renderer,
             compositor,
+            recalls: FuturesUnordered::new(),
+        })
+    }
+
+    pub fn settings(&self) -> &ChromaSettings {
+        &self.settings
+    }
+
+    /// Takes effect on the next frame. Invalid settings are rejected and the current ones kept.
+    ///
+    /// Sprite images are loaded again whenever an emitter's list of sprites changes. New emitters
+    /// start emitting with the next render, and so do emitters whose seed or backend changes,
+    /// starting over. Custom shaders are compiled again on every call.
+    pub fn set_settings(&mut self, settings: ChromaSettings) -> Result<(), SettingsError> {
+        settings.validate()?;
+
+        let loaded = settings
+            .emitters
+            .iter()
+            .enumerate()
+            .map(|(i, emitter)| match self.settings.emitters.get(i) {
+                Some(current) if current.particles.sprites == emitter.particles.sprites => Ok(None),
+                _ => AtlasImage::new(&emitter.particles.sprites).map(Some),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let shaders = custom_shader::compile_all(&settings.shaders)?;
+
+        // keep what an earlier call loaded, if the next render hasn't uploaded it yet
+        self.pending_sprites.resize_with(loaded.len(), || None);
+        self.pending_sprites = loaded
+            .into_iter()
+            .zip(self.pending_sprites.drain(..))
+            .map(|(loaded, pending)| loaded.or(pending))
+            .collect();
+        self.particle_renderers.truncate(settings.emitters.len());
+
+        for ((renderer, current), emitter) in self
+            .particle_renderers
+            .iter_mut()
+            .zip(&self.settings.emitters)
+            .zip(&settings.emitters)
+        {
+            let (current, new) = (&current.particles, &emitter.particles);
+
+            // capacity changes resize the particles in place
+            if (current.seed, current.backend) != (new.seed, new.backend) {
+                renderer.restart(new);
+            }
         }
+
+        self.custom_shader_renderer.set_shaders(shaders);
+        self.settings = settings;
+
+        Ok(())
     }
 
+    /// Number of particles lost to the capacity so far, see [`OverflowPolicy`].
+    ///
+    /// With the GPU backend, this waits for the submitted frames to finish rendering.
+    pub fn dropped_particles(&self, device: &wgpu::Device) -> u64 {
+        self.particle_renderers
+            .iter()
+            .map(|renderer| renderer.dropped(device))
+            .sum()
+    }
+
+    /// `data` holds the level of each of the [`bands`](ChromaSettings::bands).
     pub fn update(&mut self, delta: Duration, data: &[f32]) {
-        self.particle_renderer
-            .update(delta, data, &self.settings.particles);
+        for (renderer, emitter) in self
+            .particle_renderers
+            .iter_mut()
+            .zip(&self.settings.emitters)
+        {
+            renderer.update(delta, data, emitter);
+        }
+
+        self.feedback_renderer
+            .update(delta, data, &self.settings.feedback);
+        self.effect_renderer.update(delta);
+        self.custom_shader_renderer.update(delta, data);
+    }
+
+    // The commands of the last render or resize are submitted by the time of the next one, so the
+    // buffers they were uploaded with can be reused once the GPU is done with them. Mapping them
+    // back finishes with a later submit, and is only checked here without waiting for it.
+    fn recall(&mut self) {
+        self.recalls.push(self.compositor.recall().boxed());
+        self.recalls.push(self.blur_renderer.recall().boxed());
+        self.recalls.push(self.feedback_renderer.recall().boxed());
+        self.recalls.push(self.effect_renderer.recall().boxed());
+        self.recalls
+            .push(self.custom_shader_renderer.recall().boxed());
+        self.recalls.extend(
+            self.particle_renderers
+                .iter_mut()
+                .map(|renderer| renderer.recall().boxed()),
+        );
+
+        let mut context = Context::from_waker(noop_waker_ref());
+
+        while let Poll::Ready(Some(())) = self.recalls.poll_next_unpin(&mut context) {}
     }
 }
 
@@ -67,20 +289,20 @@ impl Renderer for Chroma {
         width: u32,
         height: u32,
     ) -> Vec<wgpu::CommandBuffer> {
+        self.recall();
+
         let mut encoder =
             device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
 
-        self.particle_renderer
-            .resize(device, &mut encoder, width, height);
-        self.blur_renderer
-            .resize(device, &mut encoder, width / 2, height / 2);
+        for renderer in &mut self.particle_renderers {
+            renderer.resize(device, &mut encoder, width, height);
+        }
 
-        self.low_res_targets = (
-            self.render_target_family
-                .create_target(device, width / 2, height / 2),
-            self.render_target_family
-                .create_target(device, width, height),
-        );
+        self.size = (width, height);
+        self.blur_renderer.resize(width, height);
+        self.feedback_renderer.resize(width, height);
+        self.effect_renderer.resize(width, height);
+        self.custom_shader_renderer.resize(width, height);
         self.accumulator = self
             .render_target_family
             .create_target(device, width, height);
@@ -93,40 +315,94 @@ impl Renderer for Chroma {
         device: &wgpu::Device,
         dest: &wgpu::TextureView,
     ) -> Vec<wgpu::CommandBuffer> {
+        self.recall();
+
         let mut encoder =
             device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
 
-        self.blur_renderer.render(
+        for (i, image) in self.pending_sprites.drain(..).enumerate() {
+            let image = match image {
+                Some(image) => image,
+                None => continue,
+            };
+            let atlas = SpriteAtlas::new(device, &self.render_target_family, image);
+
+            match self.particle_renderers.get_mut(i) {
+                Some(renderer) => renderer.set_atlas(atlas),
+                // past the renderers `set_settings` kept, emitters are new and had their sprites
+                // loaded
+                None => {
+                    let mut renderer = ParticleRenderer::new(
+                        device,
+                        &self.render_target_family,
+                        &self.settings.emitters[i].particles,
+                        atlas,
+                    );
+
+                    renderer.resize(device, &mut encoder, self.size.0, self.size.1);
+                    self.particle_renderers.push(renderer);
+                }
+            }
+        }
+
+        let blurred = self.blur_renderer.render(
+            device,
             &mut encoder,
+            &self.render_target_family,
             &self.accumulator,
-            &self.low_res_targets.0.view,
-            BlurDirection::Horizontal,
+            &self.settings.blur,
         );
-        self.blur_renderer.render(
+
+        if self.settings.feedback.is_enabled() {
+            self.feedback_renderer.render(
+                device,
+                &mut encoder,
+                blurred,
+                &self.accumulator.view,
+                self.settings.decay,
+                &self.settings.feedback,
+            );
+        } else {
+            self.compositor.render_transparent(
+                &mut encoder,
+                blurred,
+                &self.accumulator.view,
+                self.settings.decay,
+            );
+        }
+
+        for (renderer, emitter) in self
+            .particle_renderers
+            .iter_mut()
+            .zip(&self.settings.emitters)
+        {
+            renderer.render(device, &mut encoder, &self.accumulator.view, false, emitter);
+        }
+
+        let output = self.effect_renderer.render(
+            device,
             &mut encoder,
-            &self.low_res_targets.0,
-            &self.low_res_targets.1.view,
-            BlurDirection::Vertical,
+            &self.render_target_family,
+            &self.accumulator,
+            &self.settings.effects,
         );
-
-        self.compositor.render_transparent(
+        let output = self.custom_shader_renderer.render(
+            device,
             &mut encoder,
-            &self.low_res_targets.1,
-            &self.accumulator.view,
-            self.settings.decay,
+            &self.render_target_family,
+            output,
+            &self.accumulator,
+            &self.settings.shaders,
         );
 
-        self.particle_renderer.render(
+        self.compositor.render_solid(
             device,
             &mut encoder,
-            &self.accumulator.view,
-            false,
-            &self.settings.particles,
+            output,
+            dest,
+            &self.settings.tonemapping,
         );
 
-        self.compositor
-            .render_solid(&mut encoder, &self.accumulator, &dest);
-
         vec![encoder.finish()]
     }
 }
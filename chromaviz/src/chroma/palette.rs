@@ -0,0 +1,139 @@
+use super::SettingsError;
+
+/// Number of texels in the lookup texture a palette is baked into.
+pub const PALETTE_SIZE: u32 = 256;
+
+/// What picks a particle's color in the palette.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum PaletteKey {
+    /// Position of the emitting frequency, from the lowest to the highest.
+    Frequency,
+    /// Fraction of the lifetime that has passed.
+    Age,
+    /// Current speed, relative to the launch speed of a band at full level.
+    Velocity,
+    /// Level of the band that launched the particle.
+    Energy,
+}
+
+impl Default for PaletteKey {
+    fn default() -> Self {
+        PaletteKey::Frequency
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ColorStop {
+    /// Where the stop is on the gradient, from 0 to 1.
+    pub position: f32,
+    /// Red, green and blue, from 0 to 1.
+    pub color: [f32; 3],
+}
+
+/// A gradient that particles look their color up in.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct Palette {
+    pub key: PaletteKey,
+    /// Sorted by position. Colors are interpolated linearly between stops and held past the ends.
+    pub stops: Vec<ColorStop>,
+}
+
+impl Palette {
+    /// The full saturation hue circle, which is what particles used to be colored with.
+    pub fn rainbow() -> Self {
+        let colors = [
+            [1.0, 0.0, 0.0],
+            [1.0, 1.0, 0.0],
+            [0.0, 1.0, 0.0],
+            [0.0, 1.0, 1.0],
+            [0.0, 0.0, 1.0],
+            [1.0, 0.0, 1.0],
+            [1.0, 0.0, 0.0],
+        ];
+        let last = (colors.len() - 1) as f32;
+
+        Self {
+            key: PaletteKey::default(),
+            stops: colors
+                .iter()
+                .enumerate()
+                .map(|(i, &color)| ColorStop {
+                    position: i as f32 / last,
+                    color,
+                })
+                .collect(),
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), SettingsError> {
+        if self.stops.is_empty() {
+            return Err(SettingsError::EmptyPalette);
+        }
+
+        let mut previous = 0.0;
+
+        for (i, stop) in self.stops.iter().enumerate() {
+            let in_range = |v: f32| (0.0..=1.0).contains(&v);
+
+            if !in_range(stop.position) || stop.position < previous {
+                return Err(SettingsError::InvalidColorStop(i));
+            }
+
+            if !stop.color.iter().all(|&c| in_range(c)) {
+                return Err(SettingsError::InvalidColorStop(i));
+            }
+
+            previous = stop.position;
+        }
+
+        Ok(())
+    }
+
+    /// The color at `t`, which is clamped to `0.0..=1.0`.
+    ///
+    /// The palette must be [valid](Self::validate).
+    pub fn sample(&self, t: f32) -> [f32; 3] {
+        let t = t.clamp(0.0, 1.0);
+        let next = self.stops.iter().position(|stop| stop.position > t);
+
+        match next {
+            None => self.stops[self.stops.len() - 1].color,
+            Some(0) => self.stops[0].color,
+            Some(i) => {
+                let (a, b) = (&self.stops[i - 1], &self.stops[i]);
+                let f = (t - a.position) / (b.position - a.position);
+                let mut color = [0.0; 3];
+
+                for (c, (a, b)) in color.iter_mut().zip(a.color.iter().zip(b.color.iter())) {
+                    *c = a + (b - a) * f;
+                }
+
+                color
+            }
+        }
+    }
+
+    /// RGBA8 texels for the lookup texture, the first one at 0 and the last one at 1.
+    pub fn bake(&self) -> Vec<u8> {
+        let last = (PALETTE_SIZE - 1) as f32;
+
+        (0..PALETTE_SIZE)
+            .flat_map(|i| {
+                let [r, g, b] = self.sample(i as f32 / last);
+
+                [r, g, b, 1.0].map(|c| (c * 255.0).round() as u8)
+            })
+            .collect()
+    }
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Self::rainbow()
+    }
+}
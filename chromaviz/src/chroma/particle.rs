@@ -1,38 +1,105 @@
-use super::render_target::RenderTargetFamily;
+use super::{
+    emitter::Emitter,
+    forces::Forces,
+    gpu_particles::{GpuParticles, Step},
+    palette::{Palette, PaletteKey, PALETTE_SIZE},
+    render_target::RenderTargetFamily,
+    sprite::{Sprite, SpriteAtlas, MAX_SPRITES},
+    SettingsError,
+};
 use glam::Vec2;
-use rand::distributions::{Distribution, Uniform as UniformDistribution};
-use std::time::Duration;
+use rand::{
+    distributions::{Distribution, Uniform as UniformDistribution},
+    Rng, SeedableRng,
+};
+use rand_chacha::ChaCha8Rng;
+use std::{collections::VecDeque, future::Future, time::Duration};
 
-const MAX_PARTICLES: u64 = 0x4000;
+// position, size and palette key, then sprite, rotation, spin and age, like the compute shaders' instances
+pub(crate) const INSTANCE_SIZE: u64 = 32;
 
-#[derive(Debug, Clone)]
-struct Particle {
-    init_pos: Vec2,
-    init_vel: Vec2,
-    age: Duration,
-    lifetime: Duration,
-    hue: f32,
+// largest capacity whose GPU state fits in the smallest storage buffer range Vulkan guarantees
+pub(crate) const MAX_CAPACITY: usize = 1 << 22;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Particle {
+    pub init_pos: Vec2,
+    pub init_vel: Vec2,
+    /// Position at the particle's current age.
+    pub pos: Vec2,
+    /// Velocity at the particle's current age.
+    pub vel: Vec2,
+    pub age: Duration,
+    pub lifetime: Duration,
+    pub hue: f32,
     pub size: f32,
+    /// Index in [`ParticleSettings::sprites`].
+    pub sprite: u32,
+    /// Counterclockwise, in radians.
+    pub rotation: f32,
+    /// Counterclockwise, in radians per second.
+    pub spin: f32,
 }
 
 impl Particle {
-    pub fn pos(&self, g: Vec2) -> Vec2 {
+    // moves the particle along its exact trajectory under gravity alone, to its current age
+    fn ballistic(&mut self, g: Vec2) {
         let t = self.age.as_secs_f32();
 
-        0.5 * g * t * t + self.init_vel * t + self.init_pos
+        self.pos = 0.5 * g * t * t + self.init_vel * t + self.init_pos;
+        self.vel = g * t + self.init_vel;
+    }
+
+    /// Rotation after spinning for the particle's age.
+    pub fn angle(&self) -> f32 {
+        self.rotation + self.spin * self.age.as_secs_f32()
+    }
+
+    /// Where the particle's color is looked up in the palette, from 0 to 1.
+    pub fn palette_key(&self, key: PaletteKey, g: Vec2) -> f32 {
+        // the speed that launches a particle to a height of 1, see `velocity_for`
+        let full_speed = (2.0 * g.y.abs()).sqrt();
+
+        let t = match key {
+            PaletteKey::Frequency => self.hue,
+            PaletteKey::Age if self.lifetime.is_zero() => 1.0,
+            PaletteKey::Age => self.age.as_secs_f32() / self.lifetime.as_secs_f32(),
+            PaletteKey::Velocity => self.vel.length() / full_speed,
+            PaletteKey::Energy => (self.init_vel.length() / full_speed).powi(2),
+        };
+
+        t.clamp(0.0, 1.0)
     }
 }
 
-struct ParticleSystem {
-    pub max_particles: usize,
-    particles: Vec<Particle>,
+/// Emits particles from frequency data and ages them, without touching the GPU.
+pub struct ParticleSystem {
+    particles: VecDeque<Particle>,
+    capacity: usize,
+    overflow: OverflowPolicy,
+    dropped: u64,
+    rng: ChaCha8Rng,
+    time_since_last_emit: Duration,
+    // simulated so far, which animates the turbulence
+    time: Duration,
 }
 
 impl ParticleSystem {
-    pub fn new(max_particles: usize) -> Self {
+    /// The same seed and inputs always produce the same particles. `None` seeds from the OS.
+    pub fn new(seed: Option<u64>) -> Self {
+        let defaults = ParticleSettings::default();
+
         Self {
-            particles: Vec::with_capacity(max_particles),
-            max_particles,
+            particles: VecDeque::new(),
+            capacity: defaults.capacity,
+            overflow: defaults.overflow,
+            dropped: 0,
+            rng: match seed {
+                Some(seed) => ChaCha8Rng::seed_from_u64(seed),
+                None => ChaCha8Rng::from_entropy(),
+            },
+            time_since_last_emit: Duration::from_secs(0),
+            time: Duration::from_secs(0),
         }
     }
 
@@ -44,38 +111,310 @@ impl ParticleSystem {
         self.particles.len()
     }
 
+    /// Particles from the oldest to the newest.
     pub fn particles(&self) -> impl Iterator<Item = &Particle> {
         self.particles.iter()
     }
 
+    /// Number of particles lost to the capacity so far, either never emitted or recycled.
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+
+    pub fn clear(&mut self) {
+        self.particles.clear();
+    }
+
     pub fn emit_particle(&mut self, particle: Particle) {
-        if self.particles.len() + 1 < self.max_particles {
-            self.particles.push(particle);
+        if self.particles.len() < self.capacity {
+            self.particles.push_back(particle);
+            return;
+        }
+
+        self.dropped += 1;
+
+        if self.overflow == OverflowPolicy::RecycleOldest {
+            self.particles.pop_front();
+            self.particles.push_back(particle);
         }
     }
 
-    pub fn update(&mut self, delta: Duration) {
+    /// `emitter` must be [valid](Emitter::validate).
+    pub fn update(&mut self, delta: Duration, freq_data: &[f32], emitter: &Emitter) {
+        let settings = &emitter.particles;
+
+        self.set_capacity(settings.capacity, settings.overflow);
+        self.gen_particles(delta, freq_data, emitter);
+
         self.particles
             .retain(|particle| particle.age < particle.lifetime);
 
+        let g = settings.gravity;
+        let forces = &settings.forces;
+        let time = self.time.as_secs_f32();
+
         for particle in self.particles.iter_mut() {
             particle.age += delta;
+
+            if forces.is_enabled() {
+                let level = band_level(particle.hue, freq_data);
+
+                forces.integrate(
+                    g,
+                    &mut particle.pos,
+                    &mut particle.vel,
+                    delta.as_secs_f32(),
+                    time,
+                    level,
+                );
+
+                // drag and turbulence may take it out before its lifetime
+                if !emitter.layout.in_frame(particle.pos, particle.vel) {
+                    particle.lifetime = particle.lifetime.min(particle.age);
+                }
+            } else {
+                particle.ballistic(g);
+            }
+        }
+
+        self.time += delta;
+    }
+
+    fn set_capacity(&mut self, capacity: usize, overflow: OverflowPolicy) {
+        let excess = self.particles.len().saturating_sub(capacity);
+
+        // shrinking always sacrifices the oldest particles
+        self.particles.drain(..excess);
+        self.dropped += excess as u64;
+        self.capacity = capacity;
+        self.overflow = overflow;
+    }
+
+    fn gen_particles(&mut self, delta: Duration, freq_data: &[f32], emitter: &Emitter) {
+        let settings = &emitter.particles;
+        let freq_dist: UniformDistribution<u64> = (0..settings.frequencies).into();
+        let spread_dist: UniformDistribution<f32> = (-0.5..0.5).into();
+        let size_dist: UniformDistribution<f32> = settings.size_range.clone().into();
+        let (new_count, period) = self.due(delta, freq_data, settings);
+
+        // spawn new ones
+        for i in 0..new_count {
+            let rng = &mut self.rng;
+            let freq = {
+                let freq = freq_dist.sample(rng) as f32 / (settings.frequencies - 1) as f32;
+                let spread = spread_dist.sample(rng) * settings.frequencies_spread;
+
+                freq + spread / (settings.frequencies - 1) as f32
+            };
+            let newborn_age = delta - period.mul_f64(i as f64);
+            let position = emitter.spectrum_position(freq);
+            let (init_pos, direction) = emitter.launch(freq);
+
+            let angle = direction.y.atan2(direction.x)
+                + (spread_dist.sample(rng) * settings.angular_spread).to_radians();
+            let velocity = velocity_for(position, settings.gravity, freq_data)
+                + spread_dist.sample(rng) * settings.velocity_spread;
+
+            let init_vel = (angle.cos() * velocity, angle.sin() * velocity).into();
+            let size = size_dist.sample(rng);
+
+            // only draw what's needed, so the defaults keep the same sequence for a given seed
+            let sprite = match settings.sprites.len() {
+                1 => 0,
+                n => rng.gen_range(0, n as u32),
+            };
+            let rotation = sample_range(rng, &settings.rotation_range).to_radians();
+            let spin = sample_range(rng, &settings.spin_range).to_radians();
+
+            let forces = &settings.forces;
+            let lifetime = if forces.is_enabled() {
+                forces.lifetime(settings.gravity, &emitter.layout, init_pos, init_vel)
+            } else {
+                // until gravity would stop the particle if launched against it, which is the apex
+                // of a trajectory straight up
+                velocity.max(0.0) / settings.gravity.length()
+            };
+            let mut particle = Particle {
+                init_pos,
+                init_vel,
+                pos: init_pos,
+                vel: init_vel,
+                hue: position,
+                age: newborn_age,
+                lifetime: Duration::from_secs_f32(lifetime),
+                size,
+                sprite,
+                rotation,
+                spin,
+            };
+
+            // catch up with the time since the particle was born
+            if forces.is_enabled() {
+                let level = band_level(position, freq_data);
+
+                forces.integrate(
+                    settings.gravity,
+                    &mut particle.pos,
+                    &mut particle.vel,
+                    newborn_age.as_secs_f32(),
+                    self.time.as_secs_f32(),
+                    level,
+                );
+            } else {
+                particle.ballistic(settings.gravity);
+            }
+
+            self.emit_particle(particle);
         }
     }
+
+    // advances the emission clock, returning how many particles are due and the period between them
+    fn due(
+        &mut self,
+        delta: Duration,
+        freq_data: &[f32],
+        settings: &ParticleSettings,
+    ) -> (u128, Duration) {
+        let rate = settings.particles_per_second as f64 * emission_scale(freq_data, settings);
+        let period = Duration::from_secs_f64(1.0 / rate);
+
+        self.time_since_last_emit += delta;
+
+        let new_count = self.time_since_last_emit.as_nanos() / period.as_nanos();
+        self.time_since_last_emit -= period.mul_f64(new_count as f64);
+
+        (new_count, period)
+    }
+}
+
+// a value in `range`, without drawing from `rng` when there's only one
+fn sample_range(rng: &mut ChaCha8Rng, range: &std::ops::Range<f32>) -> f32 {
+    if range.is_empty() {
+        range.start
+    } else {
+        rng.gen_range(range.start, range.end)
+    }
+}
+
+// how much to slow down emission so the expected population fits in the capacity
+fn emission_scale(freq_data: &[f32], settings: &ParticleSettings) -> f64 {
+    if settings.overflow != OverflowPolicy::ScaleEmission || freq_data.is_empty() {
+        return 1.0;
+    }
+
+    // a particle launched to a height H lives for sqrt(2H / g), see `velocity_for`
+    let g = settings.gravity.y.abs() as f64;
+    let mean_lifetime = freq_data
+        .iter()
+        .map(|&h| (2.0 * h.max(0.0) as f64 / g).sqrt())
+        .sum::<f64>()
+        / freq_data.len() as f64;
+    // particles fall back to the floor instead of dying at the apex, ignoring drag and wind
+    let mean_lifetime = if settings.forces.is_enabled() {
+        2.0 * mean_lifetime
+    } else {
+        mean_lifetime
+    };
+    let population = settings.particles_per_second as f64 * mean_lifetime;
+
+    if population > settings.capacity as f64 {
+        settings.capacity as f64 / population
+    } else {
+        1.0
+    }
+}
+
+// level of the band at `freq`, 0 without any bands
+fn band_level(freq: f32, freq_data: &[f32]) -> f32 {
+    match freq_data.len() {
+        0 => 0.0,
+        n => freq_data[((freq * (n - 1) as f32) as usize).min(n - 1)],
+    }
+}
+
+fn velocity_for(freq: f32, g: Vec2, freq_data: &[f32]) -> f32 {
+    let target = band_level(freq, freq_data);
+
+    // U = m * g * y
+    // K = mv² / 2
+    //
+    // Problem: what should be the initial velocity to reach height H?
+    //
+    // at y = H:
+    //   > U_top = m * g * H
+    //   > K_top = 0
+    //
+    // at y = 0:
+    //   > U_bot = 0
+    //   > K_bot = U_top = m * g * H     # energy conservation!
+    //
+    // K_bot = mv² / 2
+    // U_top = mgH
+    //
+    // mv² / 2 = mgH
+    // v² / 2 = gH
+    // v² = 2gH
+    // v = sqrt(2gH)
+    (2.0 * g.y.abs() * target).sqrt()
 }
 
 #[derive(Debug, Clone)]
 struct Uniforms {
     frame_size: (f32, f32),
+    atlas_grid: (f32, f32),
 }
 
 impl Uniforms {
     fn raw(&self) -> [u8; std::mem::size_of::<Self>()] {
-        bytemuck::cast([self.frame_size.0, self.frame_size.1])
+        bytemuck::cast([
+            self.frame_size.0,
+            self.frame_size.1,
+            self.atlas_grid.0,
+            self.atlas_grid.1,
+        ])
+    }
+}
+
+/// Where particles are simulated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum ParticleBackend {
+    /// Simulated by [`ParticleSystem`], uploaded every frame.
+    Cpu,
+    /// Simulated by compute shaders, without leaving GPU memory. Scales to far more particles.
+    Gpu,
+}
+
+impl Default for ParticleBackend {
+    fn default() -> Self {
+        ParticleBackend::Cpu
+    }
+}
+
+/// What happens to new particles once [`capacity`](ParticleSettings::capacity) is reached.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum OverflowPolicy {
+    /// New particles aren't emitted until older ones die.
+    DropNewest,
+    /// New particles replace the oldest ones.
+    RecycleOldest,
+    /// The emission rate is lowered so that the expected number of particles fits, and whatever
+    /// still doesn't fit is dropped like with `DropNewest`.
+    ScaleEmission,
+}
+
+impl Default for OverflowPolicy {
+    fn default() -> Self {
+        OverflowPolicy::DropNewest
     }
 }
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 pub struct ParticleSettings {
     pub gravity: Vec2,
     pub frequencies: u64,
@@ -84,6 +423,114 @@ pub struct ParticleSettings {
     pub angular_spread: f32,
     pub velocity_spread: f32,
     pub size_range: std::ops::Range<f32>,
+    pub backend: ParticleBackend,
+    /// Maximum number of live particles. Buffers grow when it's raised.
+    pub capacity: usize,
+    pub overflow: OverflowPolicy,
+    pub palette: Palette,
+    /// Each particle picks one at random.
+    pub sprites: Vec<Sprite>,
+    /// Initial rotation of each particle, counterclockwise in degrees.
+    pub rotation_range: std::ops::Range<f32>,
+    /// Angular velocity of each particle, counterclockwise in degrees per second.
+    pub spin_range: std::ops::Range<f32>,
+    /// Forces besides gravity. Particles die when they land or leave the frame once any is enabled,
+    /// instead of at the apex of their trajectory.
+    pub forces: Forces,
+    /// Seeds the emitter when it starts, random when `None`.
+    pub seed: Option<u64>,
+}
+
+impl ParticleSettings {
+    pub fn validate(&self) -> Result<(), SettingsError> {
+        let floats = [
+            ("gravity", self.gravity.x),
+            ("gravity", self.gravity.y),
+            ("frequencies_spread", self.frequencies_spread),
+            ("angular_spread", self.angular_spread),
+            ("velocity_spread", self.velocity_spread),
+            ("size_range", self.size_range.start),
+            ("size_range", self.size_range.end),
+            ("rotation_range", self.rotation_range.start),
+            ("rotation_range", self.rotation_range.end),
+            ("spin_range", self.spin_range.start),
+            ("spin_range", self.spin_range.end),
+        ];
+
+        if let Some(&(field, _)) = floats.iter().find(|(_, v)| !v.is_finite()) {
+            return Err(SettingsError::NotFinite(field));
+        }
+
+        if self.frequencies < 2 {
+            return Err(SettingsError::TooFewFrequencies(self.frequencies));
+        }
+
+        // the emission period is counted in whole nanoseconds
+        if !(1..=1_000_000_000).contains(&self.particles_per_second) {
+            return Err(SettingsError::EmissionRate(self.particles_per_second));
+        }
+
+        if self.gravity.y >= 0.0 {
+            return Err(SettingsError::UpwardGravity(self.gravity.y));
+        }
+
+        let spreads = [
+            ("frequencies_spread", self.frequencies_spread),
+            ("angular_spread", self.angular_spread),
+            ("velocity_spread", self.velocity_spread),
+        ];
+
+        if let Some(&(field, _)) = spreads.iter().find(|(_, v)| *v < 0.0) {
+            return Err(SettingsError::NegativeSpread(field));
+        }
+
+        if self.size_range.start < 0.0 || self.size_range.is_empty() {
+            return Err(SettingsError::InvalidSizeRange(self.size_range.clone()));
+        }
+
+        let ranges = [
+            ("rotation_range", &self.rotation_range),
+            ("spin_range", &self.spin_range),
+        ];
+
+        if let Some(&(field, _)) = ranges.iter().find(|(_, r)| r.start > r.end) {
+            return Err(SettingsError::ReversedRange(field));
+        }
+
+        if !(1..=MAX_CAPACITY).contains(&self.capacity) {
+            return Err(SettingsError::InvalidCapacity(self.capacity));
+        }
+
+        if !(1..=MAX_SPRITES).contains(&self.sprites.len()) {
+            return Err(SettingsError::SpriteCount(self.sprites.len()));
+        }
+
+        self.forces.validate()?;
+        self.palette.validate()
+    }
+}
+
+impl Default for ParticleSettings {
+    fn default() -> Self {
+        Self {
+            gravity: (0.0, -4.0).into(),
+            frequencies: 32,
+            frequencies_spread: 1.0,
+            particles_per_second: 2000,
+            angular_spread: 2.0,
+            velocity_spread: 0.1,
+            size_range: 4.0..6.0,
+            backend: ParticleBackend::default(),
+            capacity: 0x4000,
+            overflow: OverflowPolicy::default(),
+            palette: Palette::default(),
+            sprites: vec![Sprite::Circle],
+            rotation_range: 0.0..0.0,
+            spin_range: 0.0..0.0,
+            forces: Forces::default(),
+            seed: None,
+        }
+    }
 }
 
 pub struct ParticleRenderer {
@@ -91,13 +538,30 @@ pub struct ParticleRenderer {
     bind_group: wgpu::BindGroup,
     staging_belt: wgpu::util::StagingBelt,
     particle_buffer: wgpu::Buffer,
+    particle_buffer_capacity: usize,
     uniform_buf: wgpu::Buffer,
+    frame_size: (f32, f32),
+    atlas: SpriteAtlas,
+    // the atlas grid changed since the uniforms were written
+    atlas_changed: bool,
+    palette_texture: wgpu::Texture,
+    // the palette that was last uploaded, if any
+    palette: Option<Palette>,
     particle_system: ParticleSystem,
-    time_since_last_emit: Duration,
+    // created on first use, along with the steps it has yet to simulate
+    gpu_particles: Option<GpuParticles>,
+    gpu_steps: Vec<Step>,
+    // dropped by GPU simulations that were thrown away
+    gpu_dropped: u64,
 }
 
 impl ParticleRenderer {
-    pub fn new(device: &wgpu::Device, family: &RenderTargetFamily) -> Self {
+    pub fn new(
+        device: &wgpu::Device,
+        family: &RenderTargetFamily,
+        settings: &ParticleSettings,
+        atlas: SpriteAtlas,
+    ) -> Self {
         let vs_module =
             device.create_shader_module(wgpu::include_spirv!("shaders/particle.vert.spv"));
 
@@ -106,19 +570,39 @@ impl ParticleRenderer {
 
         let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
             label: None,
-            entries: &[wgpu::BindGroupLayoutEntry {
-                binding: 0,
-                visibility: wgpu::ShaderStage::VERTEX,
-                ty: wgpu::BindingType::UniformBuffer {
-                    dynamic: false,
-                    min_binding_size: wgpu::BufferSize::new(std::mem::size_of::<Uniforms>() as u64),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStage::VERTEX,
+                    ty: wgpu::BindingType::UniformBuffer {
+                        dynamic: false,
+                        min_binding_size: wgpu::BufferSize::new(
+                            std::mem::size_of::<Uniforms>() as u64
+                        ),
+                    },
+                    count: None,
                 },
-                count: None,
-            }],
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStage::FRAGMENT,
+                    ty: wgpu::BindingType::SampledTexture {
+                        multisampled: false,
+                        component_type: wgpu::TextureComponentType::Float,
+                        dimension: wgpu::TextureViewDimension::D1,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStage::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler { comparison: false },
+                    count: None,
+                },
+            ],
         });
         let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
             label: None,
-            bind_group_layouts: &[&bind_group_layout],
+            bind_group_layouts: &[&bind_group_layout, &family.bind_group_layout],
             push_constant_ranges: &[],
         });
 
@@ -129,13 +613,47 @@ impl ParticleRenderer {
             mapped_at_creation: false,
         });
 
+        let palette_texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("particle palette"),
+            size: wgpu::Extent3d {
+                width: PALETTE_SIZE,
+                height: 1,
+                depth: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D1,
+            format: wgpu::TextureFormat::Rgba8Unorm,
+            usage: wgpu::TextureUsage::SAMPLED | wgpu::TextureUsage::COPY_DST,
+        });
+
+        let palette_sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: None,
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
         let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
             label: None,
             layout: &bind_group_layout,
-            entries: &[wgpu::BindGroupEntry {
-                binding: 0,
-                resource: wgpu::BindingResource::Buffer(uniform_buf.slice(..)),
-            }],
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::Buffer(uniform_buf.slice(..)),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(
+                        &palette_texture.create_view(&wgpu::TextureViewDescriptor::default()),
+                    ),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Sampler(&palette_sampler),
+                },
+            ],
         });
 
         let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
@@ -173,9 +691,9 @@ impl ParticleRenderer {
             vertex_state: wgpu::VertexStateDescriptor {
                 index_format: wgpu::IndexFormat::Uint16,
                 vertex_buffers: &[wgpu::VertexBufferDescriptor {
-                    stride: std::mem::size_of::<f32>() as u64 * 4,
+                    stride: INSTANCE_SIZE,
                     step_mode: wgpu::InputStepMode::Instance,
-                    attributes: &wgpu::vertex_attr_array![0 => Float4],
+                    attributes: &wgpu::vertex_attr_array![0 => Float4, 1 => Float4],
                 }],
             },
             sample_count: 1,
@@ -183,100 +701,56 @@ impl ParticleRenderer {
             alpha_to_coverage_enabled: false,
         });
 
-        let particle_buffer = device.create_buffer(&wgpu::BufferDescriptor {
-            label: None,
-            mapped_at_creation: false,
-            size: MAX_PARTICLES * std::mem::size_of::<f32>() as u64 * 4,
-            usage: wgpu::BufferUsage::VERTEX | wgpu::BufferUsage::COPY_DST,
-        });
-
+        let particle_buffer = create_particle_buffer(device, settings.capacity);
         let staging_belt = wgpu::util::StagingBelt::new(0x100);
 
         Self {
-            particle_system: ParticleSystem::new(MAX_PARTICLES as usize),
-            time_since_last_emit: Duration::from_secs(0),
+            particle_system: ParticleSystem::new(settings.seed),
+            gpu_particles: None,
+            gpu_steps: Vec::new(),
+            gpu_dropped: 0,
             staging_belt,
             particle_buffer,
+            particle_buffer_capacity: settings.capacity,
             uniform_buf,
+            frame_size: (0.0, 0.0),
+            atlas,
+            atlas_changed: true,
+            palette_texture,
+            palette: None,
             render_pipeline,
             bind_group,
         }
     }
 
-    fn gen_particles(&mut self, delta: Duration, freq_data: &[f32], settings: &ParticleSettings) {
-        let mut rng = rand::thread_rng();
-        let freq_dist: UniformDistribution<u64> = (0..settings.frequencies).into();
-        let spread_dist: UniformDistribution<f32> = (-0.5..0.5).into();
-        let size_dist: UniformDistribution<f32> = settings.size_range.clone().into();
-        let period = Duration::from_secs_f64(1.0 / settings.particles_per_second as f64);
-
-        self.time_since_last_emit += delta;
-
-        let new_count = self.time_since_last_emit.as_nanos() / period.as_nanos();
-        self.time_since_last_emit -= period.mul_f64(new_count as f64);
-
-        // spawn new ones
-        for i in 0..new_count {
-            let freq = {
-                let freq = freq_dist.sample(&mut rng) as f32 / (settings.frequencies - 1) as f32;
-                let spread = spread_dist.sample(&mut rng) * settings.frequencies_spread;
-
-                freq + spread / (settings.frequencies - 1) as f32
-            };
-            let newborn_age = delta - period.mul_f64(i as f64);
+    /// Throws the particles away and starts emitting again from `settings`, like a new renderer.
+    pub fn restart(&mut self, settings: &ParticleSettings) {
+        self.particle_system = ParticleSystem::new(settings.seed);
+        self.gpu_particles = None;
+        self.gpu_steps.clear();
+        self.gpu_dropped = 0;
+    }
 
-            let angle =
-                (90.0 + spread_dist.sample(&mut rng) * settings.angular_spread).to_radians();
-            let velocity = self.velocity_for(freq, settings.gravity, freq_data)
-                + spread_dist.sample(&mut rng) * settings.velocity_spread;
+    pub fn update(&mut self, delta: Duration, freq_data: &[f32], emitter: &Emitter) {
+        let settings = &emitter.particles;
 
-            let init_vel = (angle.cos() * velocity, angle.sin() * velocity).into();
+        match settings.backend {
+            ParticleBackend::Cpu => self.particle_system.update(delta, freq_data, emitter),
+            ParticleBackend::Gpu => {
+                // the CPU still keeps the emission clock and the random numbers
+                let (spawn_count, period) = self.particle_system.due(delta, freq_data, settings);
 
-            self.particle_system.emit_particle(Particle {
-                init_pos: (freq, 0.0).into(),
-                hue: freq,
-                age: newborn_age,
-                init_vel,
-                lifetime: Duration::from_secs_f32((-init_vel.y / settings.gravity.y).max(0.0)),
-                size: size_dist.sample(&mut rng),
-            });
+                self.gpu_steps.push(Step {
+                    delta,
+                    period,
+                    spawn_count: spawn_count.min(u32::MAX as u128) as u32,
+                    seed: self.particle_system.rng.gen(),
+                    bands: freq_data.to_vec(),
+                });
+            }
         }
     }
 
-    fn velocity_for(&self, freq: f32, g: Vec2, freq_data: &[f32]) -> f32 {
-        let target = freq_data[(freq * (freq_data.len() - 1) as f32) as usize];
-
-        // U = m * g * y
-        // K = mv² / 2
-        //
-        // Problem: what should be the initial velocity to reach height H?
-        //
-        // at y = H:
-        //   > U_top = m * g * H
-        //   > K_top = 0
-        //
-        // at y = 0:
-        //   > U_bot = 0
-        //   > K_bot = U_top = m * g * H     # energy conservation!
-        //
-        // K_bot = mv² / 2
-        // U_top = mgH
-        //
-        // mv² / 2 = mgH
-        // v² / 2 = gH
-        // v² = 2gH
-        // v = sqrt(2gH)
-        (2.0 * g.y.abs() * target).sqrt()
-    }
-
-    pub fn update(&mut self, delta: Duration, freq_data: &[f32], settings: &ParticleSettings) {
-        // update the particle generators
-        self.gen_particles(delta, freq_data, settings);
-
-        // update the particle system
-        self.particle_system.update(delta);
-    }
-
     pub fn resize(
         &mut self,
         device: &wgpu::Device,
@@ -284,6 +758,22 @@ impl ParticleRenderer {
         width: u32,
         height: u32,
     ) {
+        self.frame_size = (width as f32, height as f32);
+        self.write_uniforms(device, encoder);
+    }
+
+    /// Takes effect on the next render.
+    pub fn set_atlas(&mut self, atlas: SpriteAtlas) {
+        self.atlas = atlas;
+        self.atlas_changed = true;
+    }
+
+    /// Gets the upload buffers back for reuse, like `Compositor::recall`.
+    pub fn recall(&mut self) -> impl Future<Output = ()> + Send {
+        self.staging_belt.recall()
+    }
+
+    fn write_uniforms(&mut self, device: &wgpu::Device, encoder: &mut wgpu::CommandEncoder) {
         self.staging_belt
             .write_buffer(
                 encoder,
@@ -294,57 +784,212 @@ impl ParticleRenderer {
             )
             .copy_from_slice(
                 &Uniforms {
-                    frame_size: (width as f32, height as f32),
+                    frame_size: self.frame_size,
+                    atlas_grid: (self.atlas.columns as f32, self.atlas.rows as f32),
                 }
                 .raw(),
             );
 
         self.staging_belt.finish();
+        self.atlas_changed = false;
     }
 
-    pub fn render(
+    /// Number of particles lost to the capacity so far, on either backend.
+    ///
+    /// Reading the GPU counter blocks until the submitted work is done.
+    pub fn dropped(&self, device: &wgpu::Device) -> u64 {
+        let gpu_dropped = self
+            .gpu_particles
+            .as_ref()
+            .map_or(0, |gpu| gpu.dropped(device));
+
+        self.particle_system.dropped() + self.gpu_dropped + gpu_dropped
+    }
+
+    fn upload_palette(
         &mut self,
         device: &wgpu::Device,
         encoder: &mut wgpu::CommandEncoder,
-        dest: &wgpu::TextureView,
-        clear: bool,
-        settings: &ParticleSettings,
+        palette: &Palette,
+    ) {
+        if self.palette.as_ref() == Some(palette) {
+            return;
+        }
+
+        let texels = palette.bake();
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("particle palette upload"),
+            usage: wgpu::BufferUsage::COPY_SRC,
+            size: texels.len() as wgpu::BufferAddress,
+            mapped_at_creation: true,
+        });
+
+        buffer
+            .slice(..)
+            .get_mapped_range_mut()
+            .copy_from_slice(&texels);
+        buffer.unmap();
+
+        encoder.copy_buffer_to_texture(
+            wgpu::BufferCopyView {
+                buffer: &buffer,
+                layout: wgpu::TextureDataLayout {
+                    offset: 0,
+                    bytes_per_row: PALETTE_SIZE * 4,
+                    rows_per_image: 1,
+                },
+            },
+            wgpu::TextureCopyView {
+                texture: &self.palette_texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+            },
+            wgpu::Extent3d {
+                width: PALETTE_SIZE,
+                height: 1,
+                depth: 1,
+            },
+        );
+
+        self.palette = Some(palette.clone());
+    }
+
+    // height over width of the frame, which polar layouts need to stay round
+    fn aspect(&self) -> f32 {
+        match self.frame_size {
+            (width, height) if width > 0.0 => height / width,
+            _ => 1.0,
+        }
+    }
+
+    fn upload_particles(
+        &mut self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        emitter: &Emitter,
     ) {
+        let settings = &emitter.particles;
+        let aspect = self.aspect();
+
         if !self.particle_system.is_empty() {
             {
                 let mut buf = self.staging_belt.write_buffer(
                     encoder,
                     &self.particle_buffer,
                     0,
-                    wgpu::BufferSize::new(
-                        (self.particle_system.count() * 4 * std::mem::size_of::<f32>())
-                            as wgpu::BufferAddress,
-                    )
-                    .unwrap(),
+                    wgpu::BufferSize::new(self.particle_system.count() as u64 * INSTANCE_SIZE)
+                        .unwrap(),
                     device,
                 );
 
+                // mirrored particles are simulated the right way up, and flipped on the way out
+                let flip = if emitter.mirrored { -1.0 } else { 1.0 };
+
                 for (i, particle) in self.particle_system.particles().enumerate() {
-                    let pos = particle.pos(settings.gravity);
+                    let pos = emitter.layout.to_frame(particle.pos, aspect);
                     let size = {
                         let life_progress =
                             particle.age.as_secs_f32() / particle.lifetime.as_secs_f32();
                         particle.size * (1.0 - life_progress.powi(2)).max(0.0)
                     };
-                    let addr = std::mem::size_of::<f32>() * 4 * i;
+                    let key = particle.palette_key(settings.palette.key, settings.gravity);
+                    let instance = [
+                        pos.x,
+                        if emitter.mirrored { 1.0 - pos.y } else { pos.y },
+                        size,
+                        key,
+                        particle.sprite as f32,
+                        flip * particle.rotation,
+                        flip * particle.spin,
+                        particle.age.as_secs_f32(),
+                    ];
+                    let addr = INSTANCE_SIZE as usize * i;
 
-                    buf[addr + 0..addr + 4].copy_from_slice(&pos.x.to_ne_bytes());
-                    buf[addr + 4..addr + 8].copy_from_slice(&pos.y.to_ne_bytes());
-                    buf[addr + 8..addr + 12].copy_from_slice(&size.to_ne_bytes());
-                    buf[addr + 12..addr + 16].copy_from_slice(&particle.hue.to_ne_bytes());
+                    buf[addr..addr + INSTANCE_SIZE as usize]
+                        .copy_from_slice(bytemuck::cast_slice(&instance));
                 }
             }
 
             self.staging_belt.finish();
         }
+    }
+
+    pub fn render(
+        &mut self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        dest: &wgpu::TextureView,
+        clear: bool,
+        emitter: &Emitter,
+    ) {
+        let settings = &emitter.particles;
+
+        self.upload_palette(device, encoder, &settings.palette);
+        self.atlas.upload(device, encoder);
+
+        if self.atlas_changed {
+            self.write_uniforms(device, encoder);
+        }
+
+        let (instance_buffer, instance_count) = match settings.backend {
+            ParticleBackend::Cpu => {
+                // the other backend starts over when switching back to it
+                if let Some(gpu_particles) = self.gpu_particles.take() {
+                    self.gpu_dropped += gpu_particles.dropped(device);
+                }
+
+                self.gpu_steps.clear();
+
+                let needed = settings.capacity.max(self.particle_system.count());
+
+                if needed > self.particle_buffer_capacity {
+                    self.particle_buffer_capacity = needed;
+                    self.particle_buffer = create_particle_buffer(device, needed);
+                }
+
+                self.upload_particles(device, encoder, emitter);
+
+                (&self.particle_buffer, self.particle_system.count() as u32)
+            }
+            ParticleBackend::Gpu => {
+                let capacity = settings.capacity as u32;
+                let aspect = self.aspect();
+
+                // shrinking throws the particles away, as there's no telling which ones are alive
+                if let Some(gpu_particles) = &self.gpu_particles {
+                    if capacity < gpu_particles.capacity() {
+                        self.gpu_dropped += gpu_particles.dropped(device);
+                        self.gpu_particles = None;
+                    }
+                }
+
+                let gpu_particles = self
+                    .gpu_particles
+                    .get_or_insert_with(|| GpuParticles::new(device, capacity));
+                let bands = self.gpu_steps.iter().map(|s| s.bands.len()).max();
+
+                self.particle_system.clear();
+                gpu_particles.grow(device, encoder, capacity);
+                gpu_particles.reserve_bands(device, bands.unwrap_or(0));
+
+                for step in self.gpu_steps.drain(..) {
+                    gpu_particles.simulate(
+                        device,
+                        encoder,
+                        &mut self.staging_belt,
+                        &step,
+                        emitter,
+                        aspect,
+                    );
+                }
+
+                self.staging_belt.finish();
+                gpu_particles.copy_stats(encoder);
+                gpu_particles.instances()
+            }
+        };
 
         {
-            let particle_count = self.particle_system.count();
             let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                 color_attachments: &[wgpu::RenderPassColorAttachmentDescriptor {
                     attachment: dest,
@@ -363,12 +1008,21 @@ impl ParticleRenderer {
 
             rpass.set_pipeline(&self.render_pipeline);
             rpass.set_bind_group(0, &self.bind_group, &[]);
+            rpass.set_bind_group(1, &self.atlas.bind_group, &[]);
             rpass.set_vertex_buffer(
                 0,
-                self.particle_buffer
-                    .slice(..particle_count as u64 * 4 * std::mem::size_of::<f32>() as u64),
+                instance_buffer.slice(..instance_count as u64 * INSTANCE_SIZE),
             );
-            rpass.draw(0..4, 0..particle_count as u32);
+            rpass.draw(0..4, 0..instance_count);
         }
     }
 }
+
+fn create_particle_buffer(device: &wgpu::Device, capacity: usize) -> wgpu::Buffer {
+    device.create_buffer(&wgpu::BufferDescriptor {
+        label: None,
+        mapped_at_creation: false,
+        size: capacity as u64 * INSTANCE_SIZE,
+        usage: wgpu::BufferUsage::VERTEX | wgpu::BufferUsage::COPY_DST,
+    })
+}
@@ -1,3 +1,4 @@
+#[allow(dead_code)]
 pub struct RenderTarget {
     pub width: u32,
     pub height: u32,
@@ -56,6 +57,10 @@ impl RenderTarget {
     }
 }
 
+/// Format of the intermediate targets, in floating point so that trails fade smoothly all the way
+/// to black instead of banding and getting stuck on the last few 8-bit steps.
+pub const HDR_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+
 pub struct RenderTargetFamily {
     pub bind_group_layout: wgpu::BindGroupLayout,
     pub sampler: wgpu::Sampler,
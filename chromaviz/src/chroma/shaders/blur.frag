@@ -2,10 +2,13 @@
 
 layout(set = 0, binding = 0) uniform Locals {
     vec2 u_FrameSize;
+    float u_Radius;
+    uint u_Kernel;
 };
 
 layout(location = 0) in vec2 v_TexCoord;
 layout(location = 1) in vec2 v_Direction;
+layout(location = 2) flat in uint v_Pass;
 layout(location = 0) out vec4 outColor;
 layout(set = 1, binding = 5) uniform texture2D t_Color;
 layout(set = 1, binding = 6) uniform sampler s_Color;
@@ -42,7 +45,63 @@ vec4 blur13(texture2D tex, sampler smp, vec2 resolution, vec2 direction) {
   return color;
 }
 
+vec4 box(texture2D tex, sampler smp, vec2 resolution, vec2 direction) {
+    vec4 color = vec4(0.0);
+
+    for (int i = -4; i <= 4; i++) {
+        color += texture(sampler2D(tex, smp), v_TexCoord + float(i) * direction / resolution);
+    }
+
+    return color / 9.0;
+}
+
+// dual filtering, from Marius Bjørge's "Bandwidth-Efficient Rendering" (SIGGRAPH 2015)
+vec4 kawase_down(texture2D tex, sampler smp, vec2 offset) {
+    vec4 color = texture(sampler2D(tex, smp), v_TexCoord) * 4.0;
+
+    color += texture(sampler2D(tex, smp), v_TexCoord + offset);
+    color += texture(sampler2D(tex, smp), v_TexCoord - offset);
+    color += texture(sampler2D(tex, smp), v_TexCoord + vec2(offset.x, -offset.y));
+    color += texture(sampler2D(tex, smp), v_TexCoord - vec2(offset.x, -offset.y));
+
+    return color / 8.0;
+}
+
+vec4 kawase_up(texture2D tex, sampler smp, vec2 offset) {
+    vec4 color = vec4(0.0);
+
+    color += texture(sampler2D(tex, smp), v_TexCoord + vec2(-offset.x * 2.0, 0.0));
+    color += texture(sampler2D(tex, smp), v_TexCoord + vec2(-offset.x, offset.y)) * 2.0;
+    color += texture(sampler2D(tex, smp), v_TexCoord + vec2(0.0, offset.y * 2.0));
+    color += texture(sampler2D(tex, smp), v_TexCoord + vec2(offset.x, offset.y)) * 2.0;
+    color += texture(sampler2D(tex, smp), v_TexCoord + vec2(offset.x * 2.0, 0.0));
+    color += texture(sampler2D(tex, smp), v_TexCoord + vec2(offset.x, -offset.y)) * 2.0;
+    color += texture(sampler2D(tex, smp), v_TexCoord + vec2(0.0, -offset.y * 2.0));
+    color += texture(sampler2D(tex, smp), v_TexCoord + vec2(-offset.x, -offset.y)) * 2.0;
+
+    return color / 12.0;
+}
+
+const uint KERNEL_GAUSSIAN9 = 0;
+const uint KERNEL_BOX = 2;
+
+const uint PASS_DOWNSAMPLE = 2;
+const uint PASS_UPSAMPLE = 3;
+
 void main() {
-    outColor = blur13(t_Color, s_Color, u_FrameSize, v_Direction);
-    // outColor = texture(sampler2D(t_Color, s_Color), v_TexCoord);
+    vec2 direction = v_Direction * u_Radius;
+    // half a texel of the target, which is what the dual filter's offsets are measured in
+    vec2 half_texel = 0.5 * u_Radius * fwidth(v_TexCoord);
+
+    if (v_Pass == PASS_DOWNSAMPLE) {
+        outColor = kawase_down(t_Color, s_Color, half_texel);
+    } else if (v_Pass == PASS_UPSAMPLE) {
+        outColor = kawase_up(t_Color, s_Color, half_texel);
+    } else if (u_Kernel == KERNEL_GAUSSIAN9) {
+        outColor = blur9(t_Color, s_Color, u_FrameSize, direction);
+    } else if (u_Kernel == KERNEL_BOX) {
+        outColor = box(t_Color, s_Color, u_FrameSize, direction);
+    } else {
+        outColor = blur13(t_Color, s_Color, u_FrameSize, direction);
+    }
 }
@@ -2,6 +2,7 @@
 
 layout(location = 0) out vec2 v_TexCoord;
 layout(location = 1) out vec2 v_Direction;
+layout(location = 2) flat out uint v_Pass;
 
 out gl_PerVertex {
     vec4 gl_Position;
@@ -22,6 +23,7 @@ const vec2 DIRECTIONS[2] = {
 void main() {
     vec2 pos = QUAD_VERTICES[gl_VertexIndex % 4];
     v_Direction = DIRECTIONS[gl_InstanceIndex % 2];
+    v_Pass = uint(gl_InstanceIndex);
 
     vec2 uv = pos * 0.5 + 0.5;
     v_TexCoord = vec2(uv.x, 1.0 - uv.y);
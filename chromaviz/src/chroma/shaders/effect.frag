@@ -0,0 +1,123 @@
+#version 450
+
+layout(set = 0, binding = 0) uniform Locals {
+    vec4 u_Params0;
+    vec4 u_Params1;
+    vec2 u_FrameSize;
+    float u_Time;
+    uint u_Effect;
+};
+
+layout(location = 0) in vec2 v_TexCoord;
+layout(location = 0) out vec4 outColor;
+layout(set = 1, binding = 5) uniform texture2D t_Color;
+layout(set = 1, binding = 6) uniform sampler s_Color;
+
+// as in effects.rs
+const uint EFFECT_VIGNETTE = 0;
+const uint EFFECT_CHROMATIC_ABERRATION = 1;
+const uint EFFECT_GRAIN = 2;
+const uint EFFECT_SCANLINES = 3;
+const uint EFFECT_KALEIDOSCOPE = 4;
+const uint EFFECT_COLOR_GRADING = 5;
+
+const float TAU = 6.283185307179586;
+const float MIDDLE_GREY = 0.18;
+
+vec4 sample_at(vec2 uv) {
+    return texture(sampler2D(t_Color, s_Color), uv);
+}
+
+// PCG, as used for the turbulence
+uint hash(uint v) {
+    uint state = v * 747796405u + 2891336453u;
+    uint word = ((state >> ((state >> 28u) + 4u)) ^ state) * 277803737u;
+
+    return (word >> 22u) ^ word;
+}
+
+// params: strength, radius, softness
+vec4 vignette(vec4 params) {
+    // 1 in the corners
+    float d = distance(v_TexCoord, vec2(0.5)) * sqrt(2.0);
+    float shade = 1.0 - params.x * smoothstep(params.y, params.y + params.z, d);
+    vec4 color = sample_at(v_TexCoord);
+
+    return vec4(color.rgb * shade, color.a);
+}
+
+// params: offset
+vec4 chromatic_aberration(vec4 params) {
+    // outwards from the middle, as far as the offset in the corners
+    vec2 offset = (v_TexCoord - 0.5) * 2.0 * params.x;
+    vec4 color = sample_at(v_TexCoord);
+
+    color.r = sample_at(v_TexCoord + offset).r;
+    color.b = sample_at(v_TexCoord - offset).b;
+
+    return color;
+}
+
+// params: strength, size
+vec4 grain(vec4 params) {
+    uvec2 cell = uvec2(gl_FragCoord.xy / max(params.y, 1.0));
+    uint frame = uint(u_Time * 60.0);
+    float noise = float(hash(cell.x ^ hash(cell.y ^ hash(frame)))) / 4294967295.0 - 0.5;
+    vec4 color = sample_at(v_TexCoord);
+
+    return vec4(max(color.rgb + params.x * noise, 0.0), color.a);
+}
+
+// params: strength, count
+vec4 scanlines(vec4 params) {
+    float line = 0.5 + 0.5 * cos(v_TexCoord.y * params.y * TAU);
+    vec4 color = sample_at(v_TexCoord);
+
+    return vec4(color.rgb * (1.0 - params.x * line), color.a);
+}
+
+// params: segments, rotation, center
+vec4 kaleidoscope(vec4 params) {
+    float aspect = u_FrameSize.x / u_FrameSize.y;
+    // in frame heights, y up, so that segments are even
+    vec2 offset = (vec2(v_TexCoord.x, 1.0 - v_TexCoord.y) - params.zw) * vec2(aspect, 1.0);
+    float segment = TAU / params.x;
+    float angle = mod(atan(offset.y, offset.x) - params.y, segment);
+
+    // every other half of a segment is mirrored
+    angle = min(angle, segment - angle) + params.y;
+    offset = vec2(cos(angle), sin(angle)) * length(offset) / vec2(aspect, 1.0);
+
+    vec2 pos = params.zw + offset;
+
+    return sample_at(vec2(pos.x, 1.0 - pos.y));
+}
+
+// params: contrast, saturation, gamma; tint
+vec4 color_grading(vec4 params, vec3 tint) {
+    vec4 color = sample_at(v_TexCoord);
+    vec3 rgb = color.rgb * tint;
+    float luma = dot(rgb, vec3(0.2126, 0.7152, 0.0722));
+
+    rgb = max(mix(vec3(luma), rgb, params.y), 0.0);
+    rgb = MIDDLE_GREY * pow(rgb / MIDDLE_GREY, vec3(params.x));
+    rgb = pow(rgb, vec3(1.0 / params.z));
+
+    return vec4(rgb, color.a);
+}
+
+void main() {
+    if (u_Effect == EFFECT_VIGNETTE) {
+        outColor = vignette(u_Params0);
+    } else if (u_Effect == EFFECT_CHROMATIC_ABERRATION) {
+        outColor = chromatic_aberration(u_Params0);
+    } else if (u_Effect == EFFECT_GRAIN) {
+        outColor = grain(u_Params0);
+    } else if (u_Effect == EFFECT_SCANLINES) {
+        outColor = scanlines(u_Params0);
+    } else if (u_Effect == EFFECT_KALEIDOSCOPE) {
+        outColor = kaleidoscope(u_Params0);
+    } else {
+        outColor = color_grading(u_Params0, u_Params1.rgb);
+    }
+}
@@ -0,0 +1,59 @@
+#version 450
+
+layout(set = 0, binding = 0) uniform Locals {
+    vec2 u_Center;
+    vec2 u_Translation;
+    float u_Zoom;
+    float u_Rotation;
+    float u_Warp;
+    float u_WarpScale;
+    float u_WarpPhase;
+    float u_Aspect;
+};
+
+layout(location = 0) out vec2 v_TexCoord;
+
+out gl_PerVertex {
+    vec4 gl_Position;
+};
+
+// cells across and down the mesh, as in feedback.rs
+const uint GRID = 32;
+const float TAU = 6.283185307179586;
+
+const vec2 CELL_CORNERS[6] = {
+    {0.0, 0.0},
+    {1.0, 0.0},
+    {0.0, 1.0},
+    {0.0, 1.0},
+    {1.0, 0.0},
+    {1.0, 1.0},
+};
+
+vec2 rotate(vec2 v, float angle) {
+    float s = sin(angle);
+    float c = cos(angle);
+
+    return vec2(v.x * c - v.y * s, v.x * s + v.y * c);
+}
+
+void main() {
+    uint cell = gl_VertexIndex / 6;
+    vec2 corner = CELL_CORNERS[gl_VertexIndex % 6];
+    // from (0, 0) at the bottom left to (1, 1) at the top right, like emitter positions
+    vec2 pos = (vec2(cell % GRID, cell / GRID) + corner) / float(GRID);
+
+    // the previous frame is moved by the transform, so it is sampled through its inverse, in
+    // units of the frame height so that rotations stay round
+    vec2 offset = (pos - u_Center) * vec2(u_Aspect, 1.0);
+    offset = rotate(offset, -u_Rotation) / u_Zoom;
+
+    vec2 source = u_Center + offset / vec2(u_Aspect, 1.0) - u_Translation;
+    source += u_Warp * vec2(
+        sin(pos.y * u_WarpScale * TAU + u_WarpPhase),
+        cos(pos.x * u_WarpScale * TAU + u_WarpPhase * 1.3)
+    );
+
+    v_TexCoord = vec2(source.x, 1.0 - source.y);
+    gl_Position = vec4(pos * 2.0 - 1.0, 0.0, 1.0);
+}
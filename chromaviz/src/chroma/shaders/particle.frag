@@ -1,28 +1,23 @@
 #version 450
 
-layout(location = 0) in vec2 v_TexCoord;
-layout(location = 1) in float v_Size;
-layout(location = 2) in float v_Hue;
+layout(location = 0) in vec2 v_AtlasCoord;
+layout(location = 1) in float v_Key;
 layout(location = 0) out vec4 outColor;
 
-// All components are in the range [0…1], including hue.
-vec3 hsv2rgb(vec3 c) {
-    vec4 K = vec4(1.0, 2.0 / 3.0, 1.0 / 3.0, 3.0);
-    vec3 p = abs(fract(c.xxx + K.xyz) * 6.0 - K.www);
-    return c.z * mix(K.xxx, clamp(p - K.xxx, 0.0, 1.0), c.y);
-}
+layout(set = 0, binding = 1) uniform texture1D t_Palette;
+layout(set = 0, binding = 2) uniform sampler s_Palette;
+layout(set = 1, binding = 5) uniform texture2D t_Atlas;
+layout(set = 1, binding = 6) uniform sampler s_Atlas;
 
-float circle(vec2 center, float radius) {
-    float dist_px = distance(center, v_TexCoord) * v_Size;
-    vec2 uv_px = v_TexCoord * v_Size;
-    float rad_px = radius * v_Size;
+// keys of 0 and 1 land on the centers of the first and last texels
+vec3 palette(float key) {
+    float size = float(textureSize(sampler1D(t_Palette, s_Palette), 0));
 
-    return 1.0 - smoothstep(rad_px - 1.0, rad_px + 1.0, dist_px);
+    return texture(sampler1D(t_Palette, s_Palette), (key * (size - 1.0) + 0.5) / size).rgb;
 }
 
 void main() {
-    float mask = circle(vec2(0.5, 0.5), 0.25);
-    vec3 color = hsv2rgb(vec3(v_Hue, 1.0, 1.0));
+    vec4 sprite = texture(sampler2D(t_Atlas, s_Atlas), v_AtlasCoord);
 
-    outColor = vec4(color, mask);
+    outColor = vec4(palette(v_Key) * sprite.rgb, sprite.a);
 }
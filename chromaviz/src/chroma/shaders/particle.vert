@@ -1,12 +1,13 @@
 #version 450
 
-layout(location = 0) in vec4 a_Pos_Size_Hue;
-layout(location = 0) out vec2 v_TexCoord;
-layout(location = 1) out float v_Size;
-layout(location = 2) out float v_Hue;
+layout(location = 0) in vec4 a_Pos_Size_Key;
+layout(location = 1) in vec4 a_Sprite_Rotation_Spin_Age;
+layout(location = 0) out vec2 v_AtlasCoord;
+layout(location = 1) out float v_Key;
 
 layout(set = 0, binding = 0) uniform Locals {
     vec2 u_FrameSize;
+    vec2 u_AtlasGrid;
 };
 
 out gl_PerVertex {
@@ -21,11 +22,20 @@ const vec2 QUAD_VERTICES[4] = {
 };
 
 void main() {
-    v_TexCoord = QUAD_VERTICES[gl_VertexIndex % 4] + 0.5;
-    v_Size = a_Pos_Size_Hue.z;
-    v_Hue = a_Pos_Size_Hue.w;
-
-    vec2 position = a_Pos_Size_Hue.xy + QUAD_VERTICES[gl_VertexIndex % 4] * a_Pos_Size_Hue.z / u_FrameSize;
+    vec2 corner = QUAD_VERTICES[gl_VertexIndex % 4];
+    float sprite = a_Sprite_Rotation_Spin_Age.x;
+    vec3 rotation_spin_age = a_Sprite_Rotation_Spin_Age.yzw;
+    float rotation = rotation_spin_age.x + rotation_spin_age.y * rotation_spin_age.z;
+
+    // rows go down the atlas, but y goes up the quad
+    vec2 tile = vec2(mod(sprite, u_AtlasGrid.x), floor(sprite / u_AtlasGrid.x));
+    v_AtlasCoord = (tile + vec2(corner.x, -corner.y) + 0.5) / u_AtlasGrid;
+    v_Key = a_Pos_Size_Key.w;
+
+    // rotate in pixels, so that sprites keep their shape whatever the aspect ratio
+    mat2 rotate = mat2(cos(rotation), sin(rotation), -sin(rotation), cos(rotation));
+    vec2 offset = rotate * corner * a_Pos_Size_Key.z;
+    vec2 position = a_Pos_Size_Key.xy + offset / u_FrameSize;
 
     // go from (0..1) to (-1..1) coordinates
     position = position * 2 - 1;
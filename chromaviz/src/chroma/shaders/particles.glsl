@@ -0,0 +1,158 @@
+// shared by the particle compute shaders, included after `layout(local_size_x = ...) in;`
+
+struct Particle {
+    vec2 init_pos;
+    vec2 init_vel;
+    vec2 pos;
+    vec2 vel;
+    float age;
+    float lifetime;
+    float hue;
+    float size;
+    uint sprite;
+    float rotation;
+    float spin;
+};
+
+// same layout as `Params` in gpu_particles.rs
+layout(set = 0, binding = 0) uniform Params {
+    vec2 u_Gravity;
+    float u_Delta;
+    float u_Period;
+    uint u_Capacity;
+    uint u_Head;
+    uint u_SpawnCount;
+    uint u_LiveCount;
+    uint u_Frequencies;
+    float u_FrequenciesSpread;
+    float u_AngularSpread;
+    float u_VelocitySpread;
+    vec2 u_SizeRange;
+    uint u_Seed;
+    uint u_Bands;
+    uint u_Overflow;
+    uint u_PaletteKey;
+    uint u_Sprites;
+    vec2 u_RotationRange;
+    vec2 u_SpinRange;
+    float u_Time;
+    uint u_Forces;
+    float u_LinearDrag;
+    float u_QuadraticDrag;
+    vec2 u_Wind;
+    float u_TurbulenceStrength;
+    float u_TurbulenceScale;
+    float u_TurbulenceSpeed;
+    float u_TurbulenceModulation;
+    vec2 u_Position;
+    float u_Orientation;
+    uint u_Shape;
+    float u_ShapeSize;
+    float u_ShapeAngle;
+    vec2 u_FrequencyRange;
+    uint u_Mirrored;
+    uint u_Layout;
+    vec2 u_PolarCenter;
+    float u_PolarRadius;
+    float u_Aspect;
+};
+
+layout(set = 0, binding = 1) buffer Particles {
+    Particle particles[];
+};
+
+layout(set = 0, binding = 3) readonly buffer Bands {
+    float bands[];
+};
+
+// PCG hash, see "Hash Functions for GPU Rendering" (Jarzynski & Olano, 2020)
+uint hash(uint x) {
+    uint state = x * 747796405u + 2891336453u;
+    uint word = ((state >> ((state >> 28u) + 4u)) ^ state) * 277803737u;
+
+    return (word >> 22u) ^ word;
+}
+
+// `Forces`, see forces.rs
+const float STEP = 1.0 / 240.0;
+const float MAX_LIFETIME = 10.0;
+const float MARGIN = 0.05;
+// `Layout::Polar`
+const uint LAYOUT_POLAR = 1;
+
+// random value in [-1, 1] at a lattice point
+float lattice(ivec3 p) {
+    uint h = hash(uint(p.x) ^ hash(uint(p.y) ^ hash(uint(p.z))));
+
+    return float(h >> 8) / 8388608.0 - 1.0;
+}
+
+// x and y derivatives of 3D value noise
+vec2 noise_gradient(vec3 p) {
+    vec3 i = floor(p);
+    vec3 f = p - i;
+    ivec3 c = ivec3(i);
+
+    vec3 u = f * f * f * (f * (f * 6.0 - 15.0) + 10.0);
+    vec3 du = 30.0 * f * f * (f * (f - 2.0) + 1.0);
+
+    float a = lattice(c);
+    float b = lattice(c + ivec3(1, 0, 0));
+    float cc = lattice(c + ivec3(0, 1, 0));
+    float d = lattice(c + ivec3(1, 1, 0));
+    float e = lattice(c + ivec3(0, 0, 1));
+    float ff = lattice(c + ivec3(1, 0, 1));
+    float g = lattice(c + ivec3(0, 1, 1));
+    float h = lattice(c + ivec3(1, 1, 1));
+
+    float k1 = b - a;
+    float k2 = cc - a;
+    float k4 = a - b - cc + d;
+    float k5 = a - cc - e + g;
+    float k6 = a - b - e + ff;
+    float k7 = -a + b + cc - d + e - ff - g + h;
+
+    return vec2(
+        du.x * (k1 + k4 * u.y + k6 * u.z + k7 * u.y * u.z),
+        du.y * (k2 + k5 * u.z + k4 * u.x + k7 * u.z * u.x)
+    );
+}
+
+vec2 curl(vec3 p) {
+    vec2 gradient = noise_gradient(p);
+
+    return vec2(gradient.y, -gradient.x);
+}
+
+vec2 acceleration(vec2 pos, vec2 vel, float time, float level) {
+    vec2 acc = u_Wind - vel * (u_LinearDrag + u_QuadraticDrag * length(vel));
+
+    if (u_TurbulenceStrength != 0.0) {
+        float modulation = 1.0 - u_TurbulenceModulation + u_TurbulenceModulation * level;
+        vec3 field = vec3(pos * u_TurbulenceScale, time * u_TurbulenceSpeed);
+
+        acc += curl(field) * u_TurbulenceStrength * modulation;
+    }
+
+    return acc;
+}
+
+void integrate(inout vec2 pos, inout vec2 vel, float dt, float time, float level) {
+    float steps = max(ceil(dt / STEP), 1.0);
+    float h = dt / steps;
+
+    for (uint i = 0; i < uint(steps); i++) {
+        vec2 acc = u_Gravity + acceleration(pos, vel, time + float(i) * h, level);
+
+        vel += acc * h;
+        pos += vel * h;
+    }
+}
+
+// see `Layout::in_frame`
+bool in_frame(vec2 pos, vec2 vel) {
+    bool landed = pos.y < 0.0 && vel.y < 0.0;
+    bool across = u_Layout == LAYOUT_POLAR || (pos.x >= -MARGIN && pos.x <= 1.0 + MARGIN);
+
+    return !landed && across;
+}
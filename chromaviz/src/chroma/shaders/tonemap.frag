@@ -0,0 +1,33 @@
+#version 450
+
+layout(location = 0) in vec2 v_TexCoord;
+layout(location = 0) out vec4 outColor;
+
+layout(set = 0, binding = 0) uniform Uniforms {
+    float u_Exposure;
+    uint u_Operator;
+};
+layout(set = 1, binding = 5) uniform texture2D t_Color;
+layout(set = 1, binding = 6) uniform sampler s_Color;
+
+const uint TONEMAP_REINHARD = 1;
+const uint TONEMAP_ACES = 2;
+
+// Krzysztof Narkowicz's fit of the ACES filmic curve
+vec3 aces(vec3 x) {
+    return (x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14);
+}
+
+void main() {
+    vec4 color = texture(sampler2D(t_Color, s_Color), v_TexCoord);
+    vec3 rgb = max(color.rgb * u_Exposure, 0.0);
+
+    if (u_Operator == TONEMAP_REINHARD) {
+        rgb = rgb / (1.0 + rgb);
+    } else if (u_Operator == TONEMAP_ACES) {
+        rgb = aces(rgb);
+    }
+
+    // the accumulator's alpha adds up past 1, as nothing caps it anymore
+    outColor = clamp(vec4(rgb, color.a), 0.0, 1.0);
+}
@@ -0,0 +1,351 @@
+use super::{render_target::RenderTargetFamily, SettingsError};
+use std::{fs::File, path::PathBuf};
+
+/// Width and height of a sprite in the atlas.
+pub const SPRITE_SIZE: u32 = 64;
+/// Number of sprites a settings can hold.
+pub const MAX_SPRITES: usize = 256;
+
+// every mip level down to a single texel per sprite
+const MIP_LEVELS: u32 = 7;
+// subsamples per texel side when rasterizing the built-in shapes
+const SUPERSAMPLING: u32 = 4;
+
+/// What particles look like. Sprites are tinted by the palette, so white sprites take its colors.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum Sprite {
+    /// An antialiased disc, half as wide as the particle.
+    Circle,
+    /// A soft blob that fades out towards the edges of the particle.
+    Glow,
+    Ring,
+    /// A five-pointed star.
+    Star,
+    Square,
+    /// A PNG image, scaled to fill the particle.
+    Image(PathBuf),
+}
+
+impl Sprite {
+    /// Rasterizes the sprite to `SPRITE_SIZE`² RGBA8 texels.
+    pub fn load(&self) -> Result<Vec<u8>, SettingsError> {
+        match self {
+            Sprite::Image(path) => load_image(path).map_err(|e| SettingsError::SpriteImage {
+                path: path.clone(),
+                reason: e,
+            }),
+            shape => Ok(rasterize(|x, y| shape.coverage(x, y))),
+        }
+    }
+
+    // opacity at a point of the particle, from its center at (0, 0) to its edges at ±0.5
+    fn coverage(&self, x: f32, y: f32) -> f32 {
+        let r = (x * x + y * y).sqrt();
+        let inside = |b: bool| if b { 1.0 } else { 0.0 };
+
+        match self {
+            Sprite::Circle => inside(r < 0.25),
+            Sprite::Glow => (1.0 - r / 0.5).max(0.0).powi(2),
+            Sprite::Ring => inside((0.17..0.25).contains(&r)),
+            Sprite::Star => inside(in_star(x, y, 0.3, 0.12)),
+            Sprite::Square => inside(x.abs() < 0.2 && y.abs() < 0.2),
+            Sprite::Image(_) => 0.0,
+        }
+    }
+}
+
+fn rasterize(coverage: impl Fn(f32, f32) -> f32) -> Vec<u8> {
+    let samples = SPRITE_SIZE * SUPERSAMPLING;
+    let mut texels = Vec::with_capacity((SPRITE_SIZE * SPRITE_SIZE * 4) as usize);
+
+    for ty in 0..SPRITE_SIZE {
+        for tx in 0..SPRITE_SIZE {
+            let mut sum = 0.0;
+
+            for sy in 0..SUPERSAMPLING {
+                for sx in 0..SUPERSAMPLING {
+                    let x = (tx * SUPERSAMPLING + sx) as f32 + 0.5;
+                    let y = (ty * SUPERSAMPLING + sy) as f32 + 0.5;
+
+                    // rows go down the texture, but y goes up the particle
+                    sum += coverage(x / samples as f32 - 0.5, 0.5 - y / samples as f32);
+                }
+            }
+
+            let alpha = sum / (SUPERSAMPLING * SUPERSAMPLING) as f32;
+
+            texels.extend_from_slice(&[255, 255, 255, (alpha * 255.0).round() as u8]);
+        }
+    }
+
+    texels
+}
+
+// whether a point is inside a five-pointed star, pointing up
+fn in_star(x: f32, y: f32, outer: f32, inner: f32) -> bool {
+    let vertices: Vec<(f32, f32)> = (0..10)
+        .map(|i| {
+            let angle = std::f32::consts::FRAC_PI_2 + i as f32 * std::f32::consts::PI / 5.0;
+            let radius = if i % 2 == 0 { outer } else { inner };
+
+            (angle.cos() * radius, angle.sin() * radius)
+        })
+        .collect();
+
+    // even-odd ray casting
+    let mut inside = false;
+
+    for (i, &(ax, ay)) in vertices.iter().enumerate() {
+        let (bx, by) = vertices[(i + 1) % vertices.len()];
+
+        if (ay > y) != (by > y) && x < ax + (y - ay) / (by - ay) * (bx - ax) {
+            inside = !inside;
+        }
+    }
+
+    inside
+}
+
+fn load_image(path: &std::path::Path) -> Result<Vec<u8>, String> {
+    let mut decoder = png::Decoder::new(File::open(path).map_err(|e| e.to_string())?);
+
+    // always 8 bits per channel, without a palette
+    decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+
+    let (info, mut reader) = decoder.read_info().map_err(|e| e.to_string())?;
+    let mut data = vec![0; info.buffer_size()];
+
+    reader.next_frame(&mut data).map_err(|e| e.to_string())?;
+
+    let rgba: Vec<u8> = match info.color_type {
+        png::ColorType::RGBA => data,
+        png::ColorType::RGB => data
+            .chunks(3)
+            .flat_map(|p| [p[0], p[1], p[2], 255])
+            .collect(),
+        png::ColorType::GrayscaleAlpha => data
+            .chunks(2)
+            .flat_map(|p| [p[0], p[0], p[0], p[1]])
+            .collect(),
+        png::ColorType::Grayscale => data.iter().flat_map(|&v| [v, v, v, 255]).collect(),
+        png::ColorType::Indexed => unreachable!("palettes are expanded"),
+    };
+
+    Ok(resample(&rgba, info.width, info.height, SPRITE_SIZE))
+}
+
+// bilinear resampling of an RGBA8 image to a square
+fn resample(rgba: &[u8], width: u32, height: u32, size: u32) -> Vec<u8> {
+    let texel = |x: u32, y: u32, c: usize| rgba[((y * width + x) * 4) as usize + c] as f32;
+    let mut out = Vec::with_capacity((size * size * 4) as usize);
+
+    for y in 0..size {
+        for x in 0..size {
+            let sx = ((x as f32 + 0.5) * width as f32 / size as f32 - 0.5).max(0.0);
+            let sy = ((y as f32 + 0.5) * height as f32 / size as f32 - 0.5).max(0.0);
+            let (x0, y0) = ((sx as u32).min(width - 1), (sy as u32).min(height - 1));
+            let (x1, y1) = ((x0 + 1).min(width - 1), (y0 + 1).min(height - 1));
+            let (fx, fy) = (sx - x0 as f32, sy - y0 as f32);
+
+            for c in 0..4 {
+                let top = texel(x0, y0, c) * (1.0 - fx) + texel(x1, y0, c) * fx;
+                let bottom = texel(x0, y1, c) * (1.0 - fx) + texel(x1, y1, c) * fx;
+
+                out.push((top * (1.0 - fy) + bottom * fy).round() as u8);
+            }
+        }
+    }
+
+    out
+}
+
+/// Sprites packed into a grid, along with their mip levels.
+pub struct AtlasImage {
+    pub columns: u32,
+    pub rows: u32,
+    levels: Vec<Vec<u8>>,
+}
+
+impl AtlasImage {
+    pub fn new(sprites: &[Sprite]) -> Result<Self, SettingsError> {
+        let columns = (sprites.len() as f64).sqrt().ceil() as u32;
+        let rows = (sprites.len() as u32 + columns - 1) / columns;
+        let width = columns * SPRITE_SIZE;
+        let mut base = vec![0; (width * rows * SPRITE_SIZE * 4) as usize];
+
+        for (i, sprite) in sprites.iter().enumerate() {
+            let texels = sprite.load()?;
+            let (column, row) = (i as u32 % columns, i as u32 / columns);
+            let row_len = (SPRITE_SIZE * 4) as usize;
+
+            for (y, line) in texels.chunks(row_len).enumerate() {
+                let start =
+                    (((row * SPRITE_SIZE + y as u32) * width + column * SPRITE_SIZE) * 4) as usize;
+
+                base[start..start + row_len].copy_from_slice(line);
+            }
+        }
+
+        let mut levels = vec![base];
+
+        for level in 1..MIP_LEVELS {
+            let next = downsample(&levels[levels.len() - 1], width >> (level - 1));
+
+            levels.push(next);
+        }
+
+        Ok(Self {
+            columns,
+            rows,
+            levels,
+        })
+    }
+
+    fn size(&self, level: u32) -> (u32, u32) {
+        (
+            (self.columns * SPRITE_SIZE) >> level,
+            (self.rows * SPRITE_SIZE) >> level,
+        )
+    }
+}
+
+// halves an RGBA8 image, averaging colors by their alpha so transparent texels don't darken edges
+fn downsample(rgba: &[u8], width: u32) -> Vec<u8> {
+    let height = rgba.len() as u32 / 4 / width;
+    let (half_width, half_height) = (width / 2, height / 2);
+    let mut out = Vec::with_capacity((half_width * half_height * 4) as usize);
+
+    for y in 0..half_height {
+        for x in 0..half_width {
+            let texels = [(0, 0), (1, 0), (0, 1), (1, 1)]
+                .map(|(dx, dy)| ((y * 2 + dy) * width + x * 2 + dx) as usize * 4);
+            let alpha: u32 = texels.iter().map(|&i| rgba[i + 3] as u32).sum();
+
+            for c in 0..3 {
+                let weighted: u32 = texels
+                    .iter()
+                    .map(|&i| rgba[i + c] as u32 * rgba[i + 3] as u32)
+                    .sum();
+
+                out.push(weighted.checked_div(alpha).unwrap_or(0) as u8);
+            }
+
+            out.push(((alpha + 2) / 4) as u8);
+        }
+    }
+
+    out
+}
+
+/// The atlas texture, bound like a [`RenderTarget`](super::render_target::RenderTarget).
+pub struct SpriteAtlas {
+    pub columns: u32,
+    pub rows: u32,
+    pub bind_group: wgpu::BindGroup,
+    texture: wgpu::Texture,
+    // the image until it has been copied to the texture
+    pending: Option<AtlasImage>,
+}
+
+impl SpriteAtlas {
+    pub fn new(device: &wgpu::Device, family: &RenderTargetFamily, image: AtlasImage) -> Self {
+        let (width, height) = image.size(0);
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("sprite atlas"),
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth: 1,
+            },
+            mip_level_count: MIP_LEVELS,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8Unorm,
+            usage: wgpu::TextureUsage::SAMPLED | wgpu::TextureUsage::COPY_DST,
+        });
+
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout: &family.bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 5,
+                    resource: wgpu::BindingResource::TextureView(&view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 6,
+                    resource: wgpu::BindingResource::Sampler(&family.sampler),
+                },
+            ],
+        });
+
+        Self {
+            columns: image.columns,
+            rows: image.rows,
+            bind_group,
+            texture,
+            pending: Some(image),
+        }
+    }
+
+    /// Copies the image to the texture, the first time it's called.
+    pub fn upload(&mut self, device: &wgpu::Device, encoder: &mut wgpu::CommandEncoder) {
+        let image = match self.pending.take() {
+            Some(image) => image,
+            None => return,
+        };
+
+        for (level, texels) in image.levels.iter().enumerate() {
+            let (width, height) = image.size(level as u32);
+
+            // rows of a buffer-to-texture copy must be aligned
+            let align = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+            let padded_bytes_per_row = (width * 4 + align - 1) / align * align;
+
+            let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("sprite atlas upload"),
+                usage: wgpu::BufferUsage::COPY_SRC,
+                size: (padded_bytes_per_row * height) as wgpu::BufferAddress,
+                mapped_at_creation: true,
+            });
+
+            {
+                let mut data = buffer.slice(..).get_mapped_range_mut();
+
+                for (dest, row) in data
+                    .chunks_mut(padded_bytes_per_row as usize)
+                    .zip(texels.chunks((width * 4) as usize))
+                {
+                    dest[..row.len()].copy_from_slice(row);
+                }
+            }
+
+            buffer.unmap();
+
+            encoder.copy_buffer_to_texture(
+                wgpu::BufferCopyView {
+                    buffer: &buffer,
+                    layout: wgpu::TextureDataLayout {
+                        offset: 0,
+                        bytes_per_row: padded_bytes_per_row,
+                        rows_per_image: height,
+                    },
+                },
+                wgpu::TextureCopyView {
+                    texture: &self.texture,
+                    mip_level: level as u32,
+                    origin: wgpu::Origin3d::ZERO,
+                },
+                wgpu::Extent3d {
+                    width,
+                    height,
+                    depth: 1,
+                },
+            );
+        }
+    }
+}
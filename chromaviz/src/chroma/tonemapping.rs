@@ -0,0 +1,47 @@
+use super::SettingsError;
+
+/// How colors brighter than white are brought back into the output's range.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum Tonemap {
+    /// Cuts them off at white, which looks like rendering straight to the output.
+    Clamp,
+    /// Compresses highlights smoothly, never quite reaching white.
+    Reinhard,
+    /// A fit of the ACES filmic curve, with more contrast than Reinhard.
+    Aces,
+}
+
+/// How the frame is brought from the floating point targets it is rendered into to the output.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct Tonemapping {
+    pub operator: Tonemap,
+    /// Colors are multiplied by it before the operator.
+    pub exposure: f32,
+}
+
+impl Tonemapping {
+    pub fn validate(&self) -> Result<(), SettingsError> {
+        if !self.exposure.is_finite() {
+            return Err(SettingsError::NotFinite("tonemapping.exposure"));
+        }
+
+        if self.exposure < 0.0 {
+            return Err(SettingsError::NegativeExposure(self.exposure));
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for Tonemapping {
+    fn default() -> Self {
+        Self {
+            operator: Tonemap::Clamp,
+            exposure: 1.0,
+        }
+    }
+}
@@ -1,11 +1,24 @@
 pub mod chroma;
+pub mod offscreen;
+#[cfg(feature = "serde")]
+pub mod preset;
 pub mod renderer;
 
 pub mod prelude {
     pub use crate::{
-        chroma::{Chroma, ChromaSettings, ParticleSettings},
+        chroma::{
+            Blur, BlurKernel, Chroma, ChromaSettings, ChromaticAberration, ColorGrading, ColorStop,
+            CustomShader, Effect, Emitter, EmitterShape, Feedback, FeedbackAudio, Forces, Grain,
+            Kaleidoscope, Layout, OverflowPolicy, Palette, PaletteKey, ParticleBackend,
+            ParticleSettings, PostEffect, Scanlines, SettingsError, Sprite, Tonemap, Tonemapping,
+            Turbulence, Vignette,
+        },
+        offscreen::{OffscreenTarget, PngSequence, Y4mWriter},
         renderer::Renderer,
     };
+
+    #[cfg(feature = "serde")]
+    pub use crate::preset::PresetError;
 }
 
 pub use crate::prelude::*;
@@ -0,0 +1,231 @@
+use std::{
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+/// A texture that can be rendered to and read back to CPU memory.
+pub struct OffscreenTarget {
+    pub width: u32,
+    pub height: u32,
+    pub format: wgpu::TextureFormat,
+    pub texture: wgpu::Texture,
+    pub view: wgpu::TextureView,
+    buffer: wgpu::Buffer,
+    padded_bytes_per_row: u32,
+}
+
+impl OffscreenTarget {
+    /// `format` must be one of the 8-bit RGBA or BGRA formats.
+    pub fn new(
+        device: &wgpu::Device,
+        width: u32,
+        height: u32,
+        format: wgpu::TextureFormat,
+    ) -> Self {
+        assert!(
+            matches!(
+                format,
+                wgpu::TextureFormat::Rgba8Unorm
+                    | wgpu::TextureFormat::Rgba8UnormSrgb
+                    | wgpu::TextureFormat::Bgra8Unorm
+                    | wgpu::TextureFormat::Bgra8UnormSrgb
+            ),
+            "unsupported offscreen format {:?}",
+            format
+        );
+
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("offscreen target"),
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsage::OUTPUT_ATTACHMENT | wgpu::TextureUsage::COPY_SRC,
+        });
+
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        // rows of a texture-to-buffer copy must be aligned
+        let align = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+        let padded_bytes_per_row = (width * 4 + align - 1) / align * align;
+
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("offscreen readback buffer"),
+            size: (padded_bytes_per_row * height) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsage::MAP_READ | wgpu::BufferUsage::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        Self {
+            width,
+            height,
+            format,
+            texture,
+            view,
+            buffer,
+            padded_bytes_per_row,
+        }
+    }
+
+    /// Copies the current contents of the texture into the readback buffer.
+    pub fn copy(&self, device: &wgpu::Device) -> Vec<wgpu::CommandBuffer> {
+        let mut encoder =
+            device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+
+        encoder.copy_texture_to_buffer(
+            wgpu::TextureCopyView {
+                texture: &self.texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+            },
+            wgpu::BufferCopyView {
+                buffer: &self.buffer,
+                layout: wgpu::TextureDataLayout {
+                    offset: 0,
+                    bytes_per_row: self.padded_bytes_per_row,
+                    rows_per_image: self.height,
+                },
+            },
+            wgpu::Extent3d {
+                width: self.width,
+                height: self.height,
+                depth: 1,
+            },
+        );
+
+        vec![encoder.finish()]
+    }
+
+    /// Reads back the last copy as tightly packed RGBA8 rows, top to bottom.
+    ///
+    /// The commands returned by [`copy`](Self::copy) must have been submitted first.
+    pub async fn read(&self, device: &wgpu::Device) -> Result<Vec<u8>, wgpu::BufferAsyncError> {
+        let slice = self.buffer.slice(..);
+        let mapping = slice.map_async(wgpu::MapMode::Read);
+
+        device.poll(wgpu::Maintain::Wait);
+        mapping.await?;
+
+        let row_len = self.width as usize * 4;
+        let mut pixels = Vec::with_capacity(row_len * self.height as usize);
+
+        {
+            let data = slice.get_mapped_range();
+
+            for row in data.chunks(self.padded_bytes_per_row as usize) {
+                pixels.extend_from_slice(&row[..row_len]);
+            }
+        }
+
+        self.buffer.unmap();
+
+        if let wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb = self.format {
+            for pixel in pixels.chunks_mut(4) {
+                pixel.swap(0, 2);
+            }
+        }
+
+        Ok(pixels)
+    }
+}
+
+pub fn save_png<P: AsRef<Path>>(path: P, width: u32, height: u32, rgba: &[u8]) -> io::Result<()> {
+    let file = BufWriter::new(File::create(path)?);
+    let mut encoder = png::Encoder::new(file, width, height);
+
+    encoder.set_color(png::ColorType::RGBA);
+    encoder.set_depth(png::BitDepth::Eight);
+
+    encoder
+        .write_header()
+        .and_then(|mut writer| writer.write_image_data(rgba))
+        .map_err(|e| io::Error::new(io::ErrorKind::Other, e))
+}
+
+/// Writes numbered PNG frames (`frame_000000.png`, ...) into a directory.
+pub struct PngSequence {
+    dir: PathBuf,
+    next: usize,
+}
+
+impl PngSequence {
+    pub fn new<P: Into<PathBuf>>(dir: P) -> io::Result<Self> {
+        let dir = dir.into();
+
+        fs::create_dir_all(&dir)?;
+
+        Ok(Self { dir, next: 0 })
+    }
+
+    /// Number of frames written so far.
+    pub fn len(&self) -> usize {
+        self.next
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.next == 0
+    }
+
+    pub fn write(&mut self, width: u32, height: u32, rgba: &[u8]) -> io::Result<PathBuf> {
+        let path = self.dir.join(format!("frame_{:06}.png", self.next));
+
+        save_png(&path, width, height, rgba)?;
+        self.next += 1;
+
+        Ok(path)
+    }
+}
+
+/// Writes RGBA frames as an uncompressed YUV4MPEG2 (4:4:4) stream.
+///
+/// Colors are converted to limited range BT.601, which is what encoders assume for untagged input.
+pub struct Y4mWriter<W: Write> {
+    writer: W,
+    width: u32,
+    height: u32,
+    planes: Vec<u8>,
+}
+
+impl<W: Write> Y4mWriter<W> {
+    pub fn new(mut writer: W, width: u32, height: u32, fps: u32) -> io::Result<Self> {
+        writeln!(
+            writer,
+            "YUV4MPEG2 W{} H{} F{}:1 Ip A1:1 C444",
+            width, height, fps
+        )?;
+
+        Ok(Self {
+            writer,
+            width,
+            height,
+            planes: vec![0; (width * height * 3) as usize],
+        })
+    }
+
+    pub fn write(&mut self, rgba: &[u8]) -> io::Result<()> {
+        let len = (self.width * self.height) as usize;
+        let (y, uv) = self.planes.split_at_mut(len);
+        let (u, v) = uv.split_at_mut(len);
+
+        for (i, pixel) in rgba.chunks(4).take(len).enumerate() {
+            let (r, g, b) = (pixel[0] as i32, pixel[1] as i32, pixel[2] as i32);
+
+            y[i] = (((66 * r + 129 * g + 25 * b + 128) >> 8) + 16) as u8;
+            u[i] = (((-38 * r - 74 * g + 112 * b + 128) >> 8) + 128) as u8;
+            v[i] = (((112 * r - 94 * g - 18 * b + 128) >> 8) + 128) as u8;
+        }
+
+        self.writer.write_all(b"FRAME\n")?;
+        self.writer.write_all(&self.planes)
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
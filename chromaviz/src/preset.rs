@@ -0,0 +1,129 @@
+use crate::{ChromaSettings, SettingsError, Sprite};
+use std::{
+    fmt, fs, io,
+    path::{Path, PathBuf},
+};
+
+#[derive(Debug)]
+pub enum PresetError {
+    Io(io::Error),
+    Toml(toml::de::Error),
+    Json(serde_json::Error),
+    Invalid(SettingsError),
+    /// The file extension is neither `.toml` nor `.json`.
+    UnknownFormat(PathBuf),
+}
+
+impl fmt::Display for PresetError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PresetError::Io(e) => write!(f, "i/o error: {}", e),
+            PresetError::Toml(e) => write!(f, "invalid toml: {}", e),
+            PresetError::Json(e) => write!(f, "invalid json: {}", e),
+            PresetError::Invalid(e) => write!(f, "invalid settings: {}", e),
+            PresetError::UnknownFormat(path) => {
+                write!(f, "{} is neither .toml nor .json", path.display())
+            }
+        }
+    }
+}
+
+impl std::error::Error for PresetError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            PresetError::Io(e) => Some(e),
+            PresetError::Toml(e) => Some(e),
+            PresetError::Json(e) => Some(e),
+            PresetError::Invalid(e) => Some(e),
+            PresetError::UnknownFormat(_) => None,
+        }
+    }
+}
+
+impl From<io::Error> for PresetError {
+    fn from(e: io::Error) -> Self {
+        PresetError::Io(e)
+    }
+}
+
+impl From<toml::de::Error> for PresetError {
+    fn from(e: toml::de::Error) -> Self {
+        PresetError::Toml(e)
+    }
+}
+
+impl From<SettingsError> for PresetError {
+    fn from(e: SettingsError) -> Self {
+        PresetError::Invalid(e)
+    }
+}
+
+impl From<serde_json::Error> for PresetError {
+    fn from(e: serde_json::Error) -> Self {
+        PresetError::Json(e)
+    }
+}
+
+enum Format {
+    Toml,
+    Json,
+}
+
+impl Format {
+    fn of(path: &Path) -> Result<Self, PresetError> {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("toml") => Ok(Format::Toml),
+            Some("json") => Ok(Format::Json),
+            _ => Err(PresetError::UnknownFormat(path.to_owned())),
+        }
+    }
+}
+
+/// Parses and validates a TOML preset. Missing fields keep their default value.
+pub fn from_toml(s: &str) -> Result<ChromaSettings, PresetError> {
+    validated(toml::from_str(s)?)
+}
+
+/// Parses and validates a JSON preset. Missing fields keep their default value.
+pub fn from_json(s: &str) -> Result<ChromaSettings, PresetError> {
+    validated(serde_json::from_str(s)?)
+}
+
+fn validated(settings: ChromaSettings) -> Result<ChromaSettings, PresetError> {
+    settings.validate()?;
+
+    Ok(settings)
+}
+
+/// Loads a `.toml` or `.json` preset. Relative sprite image and shader paths start from its
+/// directory.
+pub fn load<P: AsRef<Path>>(path: P) -> Result<ChromaSettings, PresetError> {
+    let path = path.as_ref();
+    let format = Format::of(path)?;
+    let contents = fs::read_to_string(path)?;
+
+    let mut settings = match format {
+        Format::Toml => from_toml(&contents)?,
+        Format::Json => from_json(&contents)?,
+    };
+
+    // sprite images and shaders are relative to the preset
+    if let Some(dir) = path.parent() {
+        let sprites = settings
+            .emitters
+            .iter_mut()
+            .flat_map(|emitter| &mut emitter.particles.sprites);
+
+        for sprite in sprites {
+            if let Sprite::Image(image) = sprite {
+                *image = dir.join(&*image);
+            }
+        }
+
+        for shader in &mut settings.shaders {
+            shader.path = dir.join(&shader.path);
+        }
+    }
+
+    Ok(settings)
+}
@@ -1,3 +1,4 @@
+/// The command buffers returned by each call are expected to be submitted before the next one.
 pub trait Renderer {
     fn resize(
         &mut self,
@@ -0,0 +1,53 @@
+mod common;
+
+use chromaviz::prelude::*;
+
+use common::{brightness, device, render, settings};
+
+#[test]
+#[ignore]
+fn blur_kernels() {
+    let (device, queue) = device();
+    let with = |blur: Blur| {
+        render(
+            &device,
+            &queue,
+            ChromaSettings {
+                blur,
+                ..settings(ParticleBackend::Cpu)
+            },
+        )
+    };
+    // iterations can't be 0, but samples all on the same texel at full resolution blur nothing
+    let sharp = with(Blur {
+        kernel: BlurKernel::Box,
+        radius: 0.0,
+        iterations: 1,
+        downsample: 1,
+    });
+    let blurred: Vec<Vec<u8>> = [
+        BlurKernel::Gaussian9,
+        BlurKernel::Gaussian13,
+        BlurKernel::Box,
+        BlurKernel::DualKawase,
+    ]
+    .iter()
+    .map(|&kernel| {
+        with(Blur {
+            kernel,
+            iterations: 3,
+            ..Default::default()
+        })
+    })
+    .collect();
+
+    for (i, pixels) in blurred.iter().enumerate() {
+        assert!(brightness(pixels) > 0.0, "kernel {}", i);
+        assert_ne!(pixels, &sharp, "kernel {}", i);
+
+        // each kernel is the one selected
+        for (j, other) in blurred.iter().enumerate().skip(i + 1) {
+            assert_ne!(pixels, other, "kernels {} and {}", i, j);
+        }
+    }
+}
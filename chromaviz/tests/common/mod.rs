@@ -0,0 +1,73 @@
+// Shared by the tests that render on the GPU. They are `#[ignore]`d, since machines may have no
+// adapter at all: run them with `cargo test -- --ignored` where there is one, software adapters
+// like lavapipe included.
+
+// each test crate uses a different part
+#![allow(dead_code)]
+
+use chromaviz::prelude::*;
+use futures::executor::block_on;
+use std::time::Duration;
+
+pub const WIDTH: u32 = 160;
+pub const HEIGHT: u32 = 120;
+pub const FRAME: Duration = Duration::from_millis(16);
+
+pub fn device() -> (wgpu::Device, wgpu::Queue) {
+    let instance = wgpu::Instance::new(wgpu::BackendBit::PRIMARY);
+    let adapter = block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
+        power_preference: wgpu::PowerPreference::Default,
+        compatible_surface: None,
+    }))
+    .expect("no graphics adapter available");
+
+    block_on(adapter.request_device(
+        &wgpu::DeviceDescriptor {
+            features: wgpu::Features::empty(),
+            limits: wgpu::Limits::default(),
+            shader_validation: true,
+        },
+        None,
+    ))
+    .unwrap()
+}
+
+pub fn settings(backend: ParticleBackend) -> ChromaSettings {
+    ChromaSettings {
+        emitters: vec![Emitter {
+            particles: ParticleSettings {
+                frequencies: 8,
+                seed: Some(0),
+                backend,
+                ..Default::default()
+            },
+            ..Default::default()
+        }],
+        ..Default::default()
+    }
+}
+
+/// The last of 30 frames.
+pub fn render(device: &wgpu::Device, queue: &wgpu::Queue, settings: ChromaSettings) -> Vec<u8> {
+    let format = wgpu::TextureFormat::Rgba8UnormSrgb;
+    let target = OffscreenTarget::new(device, WIDTH, HEIGHT, format);
+    let mut renderer = Chroma::new(device, WIDTH, HEIGHT, format, settings).unwrap();
+
+    queue.submit(renderer.resize(device, WIDTH, HEIGHT));
+
+    for _ in 0..30 {
+        renderer.update(FRAME, &[0.5; 8]);
+        queue.submit(renderer.render(device, &target.view));
+    }
+
+    queue.submit(target.copy(device));
+    block_on(target.read(device)).unwrap()
+}
+
+pub fn brightness(pixels: &[u8]) -> f64 {
+    pixels
+        .chunks(4)
+        .map(|p| p[..3].iter().map(|&c| c as f64).sum::<f64>())
+        .sum::<f64>()
+        / pixels.len() as f64
+}
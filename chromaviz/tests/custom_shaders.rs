@@ -0,0 +1,146 @@
+mod common;
+
+use chromaviz::prelude::*;
+use std::path::PathBuf;
+
+#[cfg(feature = "glsl")]
+use common::{brightness, device, render, settings};
+
+fn example() -> CustomShader {
+    PathBuf::from(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/../presets/shaders/ripple.frag"
+    ))
+    .into()
+}
+
+fn compile_source(name: &str, source: &str) -> Result<Vec<u32>, SettingsError> {
+    let path = std::env::temp_dir().join(format!(
+        "chromaviz-shader-{}-{}.frag",
+        name,
+        std::process::id()
+    ));
+    std::fs::write(&path, source).unwrap();
+
+    let result = CustomShader::from(path.clone()).compile();
+    std::fs::remove_file(&path).unwrap();
+
+    result
+}
+
+#[test]
+fn missing_file() {
+    let shader = CustomShader::from(PathBuf::from("does/not/exist.frag"));
+
+    assert!(matches!(
+        shader.compile(),
+        Err(SettingsError::CustomShader { path, .. }) if path == shader.path
+    ));
+}
+
+#[cfg(feature = "glsl")]
+#[test]
+fn compiles() {
+    let spirv = example().compile().unwrap();
+
+    // the SPIR-V magic number
+    assert_eq!(spirv[0], 0x0723_0203);
+}
+
+#[cfg(feature = "glsl")]
+#[test]
+fn compile_errors() {
+    match compile_source("broken", "void main() {\n    outColor = u_Nope;\n}\n") {
+        Err(SettingsError::CustomShader { reason, .. }) => {
+            assert!(reason.contains("u_Nope"), "{}", reason)
+        }
+        result => panic!("expected a compile error, got {:?}", result),
+    }
+}
+
+#[cfg(feature = "glsl")]
+#[test]
+fn rejects_other_bindings() {
+    for (name, declaration, reason) in &[
+        (
+            "unbound",
+            "layout(set = 0, binding = 3) uniform texture2D t_Extra;",
+            "set 0, binding 3",
+        ),
+        (
+            "integer",
+            "layout(set = 1, binding = 5) uniform utexture2D t_Extra;",
+            "set 1, binding 5",
+        ),
+        (
+            "set",
+            "layout(set = 3, binding = 0) uniform sampler s_Extra;",
+            "set 3, binding 0",
+        ),
+        (
+            "block",
+            "layout(set = 0, binding = 0) uniform Extra { float u_Extra; };",
+            "uniform blocks",
+        ),
+        (
+            "storage",
+            "layout(set = 0, binding = 2) buffer Extra { float b_Extra[]; };",
+            "set 0, binding 2",
+        ),
+    ] {
+        let source = format!(
+            "{}\nvoid main() {{\n    outColor = vec4(band(0.5));\n}}\n",
+            declaration
+        );
+
+        match compile_source(name, &source) {
+            Err(SettingsError::CustomShader { reason: actual, .. }) => {
+                assert!(actual.contains(reason), "{}: {}", name, actual)
+            }
+            result => panic!("{}: expected a binding error, got {:?}", name, result),
+        }
+    }
+}
+
+#[cfg(not(feature = "glsl"))]
+#[test]
+fn needs_the_glsl_feature() {
+    assert!(matches!(
+        compile_source("plain", "void main() {\n    outColor = vec4(1.0);\n}\n"),
+        Err(SettingsError::CustomShader { .. })
+    ));
+    assert!(example().compile().is_err());
+}
+
+#[cfg(feature = "glsl")]
+#[test]
+#[ignore]
+fn custom_shaders() {
+    let (device, queue) = device();
+
+    let plain = render(&device, &queue, settings(ParticleBackend::Cpu));
+    let with = |shaders: Vec<CustomShader>| {
+        render(
+            &device,
+            &queue,
+            ChromaSettings {
+                shaders,
+                ..settings(ParticleBackend::Cpu)
+            },
+        )
+    };
+
+    // disabled shaders aren't even read
+    assert_eq!(
+        with(vec![CustomShader {
+            path: "does/not/exist.frag".into(),
+            enabled: false,
+        }]),
+        plain
+    );
+
+    let rippled = with(vec![example()]);
+
+    assert!(brightness(&rippled) > 0.0);
+    assert_ne!(rippled, plain);
+}
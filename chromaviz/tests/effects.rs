@@ -0,0 +1,118 @@
+mod common;
+
+use chromaviz::prelude::*;
+
+use common::{brightness, device, render, settings};
+
+fn effects(effects: Vec<Effect>) -> Result<(), SettingsError> {
+    ChromaSettings {
+        effects: effects.into_iter().map(PostEffect::from).collect(),
+        ..Default::default()
+    }
+    .validate()
+}
+
+#[test]
+fn defaults_are_valid() {
+    assert_eq!(
+        effects(vec![
+            Effect::Vignette(Vignette::default()),
+            Effect::ChromaticAberration(ChromaticAberration::default()),
+            Effect::Grain(Grain::default()),
+            Effect::Scanlines(Scanlines::default()),
+            Effect::Kaleidoscope(Kaleidoscope::default()),
+            Effect::ColorGrading(ColorGrading::default()),
+        ]),
+        Ok(())
+    );
+}
+
+#[test]
+fn validation() {
+    assert_eq!(
+        effects(vec![
+            Effect::Grain(Grain::default()),
+            Effect::Kaleidoscope(Kaleidoscope {
+                segments: 0,
+                ..Default::default()
+            }),
+        ]),
+        Err(SettingsError::InvalidEffect(1))
+    );
+    assert_eq!(
+        effects(vec![Effect::ColorGrading(ColorGrading {
+            gamma: 0.0,
+            ..Default::default()
+        })]),
+        Err(SettingsError::InvalidEffect(0))
+    );
+    assert_eq!(
+        effects(vec![Effect::Scanlines(Scanlines {
+            strength: 1.5,
+            ..Default::default()
+        })]),
+        Err(SettingsError::InvalidEffect(0))
+    );
+    assert_eq!(
+        effects(vec![Effect::ChromaticAberration(ChromaticAberration {
+            offset: f32::NAN,
+        })]),
+        Err(SettingsError::NotFinite("effects"))
+    );
+
+    // disabled effects are checked too, so that enabling them can't fail
+    let settings = ChromaSettings {
+        effects: vec![PostEffect {
+            enabled: false,
+            effect: Effect::Grain(Grain {
+                size: 0.0,
+                ..Default::default()
+            }),
+        }],
+        ..Default::default()
+    };
+
+    assert_eq!(settings.validate(), Err(SettingsError::InvalidEffect(0)));
+}
+
+#[test]
+#[ignore]
+fn post_effects() {
+    let (device, queue) = device();
+
+    let plain = render(&device, &queue, settings(ParticleBackend::Cpu));
+    let with = |effects: Vec<PostEffect>| {
+        render(
+            &device,
+            &queue,
+            ChromaSettings {
+                effects,
+                ..settings(ParticleBackend::Cpu)
+            },
+        )
+    };
+
+    // disabled effects are skipped
+    assert_eq!(
+        with(vec![PostEffect {
+            enabled: false,
+            effect: Effect::Grain(Grain::default()),
+        }]),
+        plain
+    );
+
+    let greyscale = with(vec![
+        Effect::Kaleidoscope(Kaleidoscope::default()).into(),
+        Effect::Vignette(Vignette::default()).into(),
+        Effect::ColorGrading(ColorGrading {
+            saturation: 0.0,
+            ..Default::default()
+        })
+        .into(),
+    ]);
+
+    assert!(brightness(&greyscale) > 0.0);
+    assert!(greyscale
+        .chunks(4)
+        .all(|p| p[0].max(p[1]).max(p[2]) - p[0].min(p[1]).min(p[2]) <= 2));
+}
@@ -0,0 +1,290 @@
+mod common;
+
+use std::time::Duration;
+
+use chromaviz::{chroma::ParticleSystem, prelude::*};
+use glam::Vec2;
+
+use common::{device, FRAME, HEIGHT, WIDTH};
+
+fn close(a: Vec2, b: Vec2) -> bool {
+    (a - b).length() < 1e-5
+}
+
+fn emitter(f: impl FnOnce(&mut Emitter)) -> Result<(), SettingsError> {
+    let mut settings = ChromaSettings::default();
+
+    f(&mut settings.emitters[0]);
+    settings.validate()
+}
+
+#[test]
+fn default_is_a_line_across_the_floor() {
+    let emitter = Emitter::default();
+
+    for &freq in &[0.0, 0.25, 1.0] {
+        let (pos, direction) = emitter.launch(freq);
+
+        assert!(close(pos, Vec2::new(freq, 0.0)), "{:?}", pos);
+        assert!(close(direction, Vec2::new(0.0, 1.0)), "{:?}", direction);
+        assert_eq!(emitter.spectrum_position(freq), freq);
+    }
+}
+
+#[test]
+fn shapes() {
+    let circle = Emitter {
+        shape: EmitterShape::Circle { radius: 0.25 },
+        position: (0.5, 0.5).into(),
+        ..Default::default()
+    };
+
+    // from the bottom, up the left side
+    assert!(close(circle.launch(0.0).0, Vec2::new(0.5, 0.25)));
+    assert!(close(circle.launch(0.25).0, Vec2::new(0.25, 0.5)));
+    assert!(close(circle.launch(0.5).1, Vec2::new(0.0, 1.0)));
+
+    let arc = Emitter {
+        shape: EmitterShape::Arc {
+            radius: 1.0,
+            angle: 90.0,
+        },
+        position: Vec2::zero(),
+        ..Default::default()
+    };
+    let left = Vec2::new(-0.5_f32.sqrt(), 0.5_f32.sqrt());
+
+    assert!(close(arc.launch(0.0).0, left));
+    assert!(close(arc.launch(0.0).1, left));
+
+    let point = Emitter {
+        shape: EmitterShape::Point,
+        orientation: 90.0,
+        ..Default::default()
+    };
+
+    // launching to the left
+    assert!(close(point.launch(0.7).0, Vec2::new(0.5, 0.0)));
+    assert!(close(point.launch(0.7).1, Vec2::new(-1.0, 0.0)));
+}
+
+#[test]
+fn frequency_ranges() {
+    let highs = Emitter {
+        frequency_range: 0.5..1.0,
+        ..Default::default()
+    };
+
+    assert_eq!(highs.spectrum_position(0.0), 0.5);
+    assert_eq!(highs.spectrum_position(0.5), 0.75);
+
+    let mut system = ParticleSystem::new(Some(0));
+    let bands: Vec<_> = (0..8)
+        .map(|band| if band < 4 { 0.0 } else { 0.5 })
+        .collect();
+
+    system.update(Duration::from_millis(16), &bands, &highs);
+
+    // only the loud half of the spectrum launches anything
+    assert!(system.count() > 0);
+    assert!(system
+        .particles()
+        .all(|p| p.hue >= 0.45 && p.init_vel.y > 0.5));
+}
+
+#[test]
+fn radial_bursts_go_everywhere() {
+    let burst = Emitter {
+        shape: EmitterShape::Circle { radius: 0.1 },
+        position: (0.5, 0.5).into(),
+        ..Default::default()
+    };
+    let mut system = ParticleSystem::new(Some(0));
+
+    system.update(Duration::from_millis(16), &[0.5; 32], &burst);
+
+    // particles launched downwards live as long as the others
+    assert!(system.particles().any(|p| p.init_vel.y < -0.5));
+    assert!(system
+        .particles()
+        .all(|p| p.lifetime > Duration::from_millis(300)));
+}
+
+#[test]
+fn validation() {
+    assert_eq!(
+        ChromaSettings {
+            emitters: Vec::new(),
+            ..Default::default()
+        }
+        .validate(),
+        Err(SettingsError::NoEmitters)
+    );
+    assert_eq!(
+        emitter(|e| e.shape = EmitterShape::Circle { radius: -1.0 }),
+        Err(SettingsError::InvalidShape(EmitterShape::Circle {
+            radius: -1.0
+        }))
+    );
+    assert_eq!(
+        emitter(|e| e.frequency_range = 0.5..1.5),
+        Err(SettingsError::FrequencyRange(0.5..1.5))
+    );
+    assert_eq!(
+        emitter(|e| e.frequency_range = 0.5..0.25),
+        Err(SettingsError::ReversedRange("frequency_range"))
+    );
+    assert_eq!(
+        emitter(|e| e.orientation = f32::NAN),
+        Err(SettingsError::NotFinite("orientation"))
+    );
+}
+
+#[test]
+fn bands_cover_every_emitter() {
+    let mut settings = ChromaSettings::default();
+
+    settings.emitters.push(Emitter {
+        particles: ParticleSettings {
+            frequencies: 64,
+            ..Default::default()
+        },
+        ..Default::default()
+    });
+
+    assert_eq!(settings.bands(), 64);
+}
+
+#[test]
+fn polar_layouts_wrap_around_a_circle() {
+    let polar = Layout::Polar {
+        center: (0.5, 0.5).into(),
+        radius: 0.25,
+    };
+    let wide = 9.0 / 16.0;
+
+    // from the bottom, up the left side, with distances in frame heights
+    assert!(close(
+        polar.to_frame((0.0, 0.0).into(), 1.0),
+        Vec2::new(0.5, 0.25)
+    ));
+    assert!(close(
+        polar.to_frame((0.25, 0.0).into(), 1.0),
+        Vec2::new(0.25, 0.5)
+    ));
+    assert!(close(
+        polar.to_frame((0.5, 0.25).into(), wide),
+        Vec2::new(0.5, 1.0)
+    ));
+    assert!(close(
+        polar.to_frame((0.75, 0.25).into(), wide),
+        Vec2::new(0.5 + 0.5 * wide, 0.5)
+    ));
+    assert_eq!(
+        Layout::Cartesian.to_frame((0.3, 0.7).into(), wide),
+        Vec2::new(0.3, 0.7)
+    );
+
+    // going around never leaves the frame, only landing on the circle does
+    assert!(polar.in_frame((1.5, 0.5).into(), (1.0, 0.0).into()));
+    assert!(!polar.in_frame((0.5, -0.01).into(), (0.0, -1.0).into()));
+    assert!(!Layout::Cartesian.in_frame((1.5, 0.5).into(), (1.0, 0.0).into()));
+}
+
+#[test]
+fn particles_above_the_frame_fall_back() {
+    let polar = Layout::Polar {
+        center: (0.5, 0.5).into(),
+        radius: 0.2,
+    };
+    let (above, rising) = (Vec2::new(0.5, 1.5), Vec2::new(0.0, 1.0));
+
+    for layout in &[Layout::Cartesian, polar] {
+        assert!(layout.in_frame(above, rising));
+        assert!(layout.in_frame(above, -rising));
+    }
+}
+
+#[test]
+fn polar_validation() {
+    let layout = Layout::Polar {
+        center: (0.5, 0.5).into(),
+        radius: -0.1,
+    };
+
+    assert_eq!(
+        emitter(|e| e.layout = layout.clone()),
+        Err(SettingsError::InvalidLayout(layout))
+    );
+    assert_eq!(
+        emitter(|e| {
+            e.layout = Layout::Polar {
+                center: (f32::INFINITY, 0.5).into(),
+                radius: 0.1,
+            }
+        }),
+        Err(SettingsError::NotFinite("layout"))
+    );
+}
+
+#[test]
+#[ignore]
+fn hot_reload_restarts_changed_emitters() {
+    let (device, queue) = device();
+    // outlives the run, so all but the first 64 particles are dropped
+    let overflowing = Emitter {
+        particles: ParticleSettings {
+            frequencies: 8,
+            capacity: 64,
+            gravity: (0.0, -0.01).into(),
+            seed: Some(0),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let with = |emitters: Vec<Emitter>| ChromaSettings {
+        emitters,
+        ..Default::default()
+    };
+    let format = wgpu::TextureFormat::Rgba8UnormSrgb;
+    let target = OffscreenTarget::new(&device, WIDTH, HEIGHT, format);
+    let two = with(vec![overflowing.clone(), overflowing.clone()]);
+    let mut renderer = Chroma::new(&device, WIDTH, HEIGHT, format, two.clone()).unwrap();
+
+    for _ in 0..30 {
+        renderer.update(FRAME, &[0.5; 8]);
+        queue.submit(renderer.render(&device, &target.view));
+    }
+
+    let dropped = renderer.dropped_particles(&device);
+
+    assert!(dropped > 0);
+
+    // unchanged emitters keep going
+    renderer.set_settings(two.clone()).unwrap();
+    assert_eq!(renderer.dropped_particles(&device), dropped);
+
+    // removed and added back before the next render, the second one is new
+    renderer
+        .set_settings(with(vec![overflowing.clone()]))
+        .unwrap();
+    renderer.set_settings(two).unwrap();
+    assert_eq!(renderer.dropped_particles(&device), dropped / 2);
+
+    // more room keeps the particles, and the count of those dropped
+    let mut roomier = overflowing.clone();
+
+    roomier.particles.capacity = 128;
+    renderer
+        .set_settings(with(vec![roomier, overflowing.clone()]))
+        .unwrap();
+    assert_eq!(renderer.dropped_particles(&device), dropped / 2);
+
+    let mut reseeded = overflowing.clone();
+
+    reseeded.particles.seed = Some(1);
+    renderer
+        .set_settings(with(vec![reseeded, overflowing]))
+        .unwrap();
+    assert_eq!(renderer.dropped_particles(&device), 0);
+}
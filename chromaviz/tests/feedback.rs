@@ -0,0 +1,116 @@
+mod common;
+
+use chromaviz::prelude::*;
+
+use common::{brightness, device, render, settings};
+
+fn feedback(f: impl FnOnce(&mut Feedback)) -> Result<(), SettingsError> {
+    let mut settings = ChromaSettings::default();
+
+    f(&mut settings.feedback);
+    settings.validate()
+}
+
+#[test]
+fn disabled_by_default() {
+    assert!(!Feedback::default().is_enabled());
+
+    // moving the center of nothing still does nothing
+    assert!(!Feedback {
+        center: (0.2, 0.2).into(),
+        ..Default::default()
+    }
+    .is_enabled());
+    assert!(Feedback {
+        zoom: 1.01,
+        ..Default::default()
+    }
+    .is_enabled());
+    assert!(Feedback {
+        audio: FeedbackAudio {
+            warp: 0.01,
+            ..Default::default()
+        },
+        ..Default::default()
+    }
+    .is_enabled());
+}
+
+#[test]
+fn levels() {
+    let bass = FeedbackAudio {
+        frequency_range: 0.0..0.25,
+        ..Default::default()
+    };
+    let bands = [1.0, 0.5, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0];
+
+    assert_eq!(bass.level(&bands), 0.75);
+    assert_eq!(FeedbackAudio::default().level(&bands), 0.1875);
+    assert_eq!(bass.level(&[]), 0.0);
+
+    // an empty range still reads the band it falls in
+    let top = FeedbackAudio {
+        frequency_range: 1.0..1.0,
+        ..Default::default()
+    };
+
+    assert_eq!(top.level(&[0.0, 0.5]), 0.5);
+}
+
+#[test]
+fn validation() {
+    assert_eq!(
+        feedback(|f| f.zoom = 0.0),
+        Err(SettingsError::FeedbackZoom(0.0))
+    );
+    // shrinking to nothing at full level
+    assert_eq!(
+        feedback(|f| f.audio.zoom = -1.0),
+        Err(SettingsError::FeedbackZoom(0.0))
+    );
+    assert_eq!(
+        feedback(|f| f.audio.frequency_range = 0.5..0.25),
+        Err(SettingsError::ReversedRange(
+            "feedback.audio.frequency_range"
+        ))
+    );
+    assert_eq!(
+        feedback(|f| f.audio.frequency_range = 0.5..2.0),
+        Err(SettingsError::FrequencyRange(0.5..2.0))
+    );
+    assert_eq!(
+        feedback(|f| f.rotation = f32::NAN),
+        Err(SettingsError::NotFinite("feedback"))
+    );
+    assert_eq!(
+        feedback(|f| {
+            f.zoom = 1.05;
+            f.audio.zoom = -0.5;
+        }),
+        Ok(())
+    );
+}
+
+#[test]
+#[ignore]
+fn feedback_warp() {
+    let (device, queue) = device();
+
+    let still = render(&device, &queue, settings(ParticleBackend::Cpu));
+    let swirl = render(
+        &device,
+        &queue,
+        ChromaSettings {
+            feedback: Feedback {
+                zoom: 1.05,
+                rotation: 3.0,
+                warp: 0.01,
+                ..Default::default()
+            },
+            ..settings(ParticleBackend::Cpu)
+        },
+    );
+
+    assert!(brightness(&swirl) > 0.0);
+    assert_ne!(still, swirl);
+}
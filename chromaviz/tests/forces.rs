@@ -0,0 +1,206 @@
+use std::time::Duration;
+
+use chromaviz::chroma::{
+    Emitter, EmitterShape, Forces, Layout, Particle, ParticleSettings, ParticleSystem, Turbulence,
+};
+use glam::Vec2;
+
+const FRAME: Duration = Duration::from_millis(16);
+const G: Vec2 = glam::const_vec2!([0.0, -4.0]);
+
+fn run(emitter: &Emitter, frames: usize) -> Vec<Particle> {
+    let mut system = ParticleSystem::new(Some(0));
+
+    for _ in 0..frames {
+        system.update(FRAME, &[0.5; 8], emitter);
+    }
+
+    system.particles().cloned().collect()
+}
+
+fn simulate(forces: Forces, frames: usize) -> Vec<Particle> {
+    let emitter = Emitter {
+        particles: ParticleSettings {
+            frequencies: 8,
+            forces,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+
+    run(&emitter, frames)
+}
+
+// thrown straight up at 2 from `position`, falling back after 2v/g = 1 second
+fn fountain(position: Vec2, layout: Layout, forces: Forces) -> Emitter {
+    Emitter {
+        shape: EmitterShape::Point,
+        position,
+        layout,
+        particles: ParticleSettings {
+            frequencies: 8,
+            angular_spread: 0.0,
+            velocity_spread: 0.0,
+            forces,
+            ..Default::default()
+        },
+        ..Default::default()
+    }
+}
+
+// the oldest of the particles still alive, in seconds
+fn oldest(particles: &[Particle]) -> f32 {
+    particles
+        .iter()
+        .map(|p| p.age.as_secs_f32())
+        .fold(0.0, f32::max)
+}
+
+fn turbulent() -> Forces {
+    Forces {
+        turbulence: Turbulence {
+            strength: 2.0,
+            ..Default::default()
+        },
+        ..Default::default()
+    }
+}
+
+#[test]
+fn disabled_by_default() {
+    assert!(!Forces::default().is_enabled());
+    assert!(turbulent().is_enabled());
+}
+
+#[test]
+fn lands_on_the_floor() {
+    // barely any, but enough to integrate them
+    let breeze = Forces {
+        wind: (0.001, 0.0).into(),
+        ..Default::default()
+    };
+    // long enough that the first ones are gone
+    let particles = run(&fountain((0.5, 0.0).into(), Layout::Cartesian, breeze), 90);
+
+    for particle in &particles {
+        let lifetime = particle.lifetime.as_secs_f32();
+
+        assert!((lifetime - 1.0).abs() < 0.01, "{:?}", particle);
+    }
+
+    // faded out all the way as they land
+    assert!(oldest(&particles) > 0.95 && oldest(&particles) < 1.0 + FRAME.as_secs_f32());
+}
+
+#[test]
+fn drag_lands_sooner() {
+    let drag = Forces {
+        linear_drag: 2.0,
+        ..Default::default()
+    };
+    let particles = run(&fountain((0.5, 0.0).into(), Layout::Cartesian, drag), 90);
+
+    // killed when they land after about 0.8 seconds, before the lifetime ignoring drag
+    assert!(oldest(&particles) < 0.85, "{}", oldest(&particles));
+}
+
+#[test]
+fn leaves_the_frame() {
+    let forces = Forces {
+        wind: (4.0, 0.0).into(),
+        ..Default::default()
+    };
+    // blown past the right edge long before landing
+    let particles = run(&fountain((0.9, 0.0).into(), Layout::Cartesian, forces), 90);
+
+    assert!(!particles.is_empty());
+    assert!(oldest(&particles) < 0.5, "{}", oldest(&particles));
+}
+
+#[test]
+fn going_around_stays_in_the_frame() {
+    let forces = Forces {
+        wind: (4.0, 0.0).into(),
+        ..Default::default()
+    };
+    let polar = Layout::Polar {
+        center: (0.5, 0.5).into(),
+        radius: 0.2,
+    };
+    // blown around the circle until it falls back onto it
+    let particles = run(&fountain((0.9, 0.0).into(), polar, forces), 90);
+
+    assert!(oldest(&particles) > 0.95 && oldest(&particles) < 1.0 + FRAME.as_secs_f32());
+}
+
+#[test]
+fn drag_slows_down() {
+    let integrate = |forces: &Forces| {
+        let (mut pos, mut vel) = (Vec2::new(0.5, 0.0), Vec2::new(0.0, 2.0));
+
+        forces.integrate(G, &mut pos, &mut vel, 0.25, 0.0, 0.0);
+        (pos, vel)
+    };
+    let (free_pos, free_vel) = integrate(&Forces::default());
+    let (pos, vel) = integrate(&Forces {
+        linear_drag: 1.0,
+        quadratic_drag: 1.0,
+        ..Default::default()
+    });
+
+    // without drag, the integrator follows the exact trajectory
+    assert!((free_pos.y - 0.375).abs() < 1e-2, "{:?}", free_pos);
+    assert!((free_vel.y - 1.0).abs() < 1e-4, "{:?}", free_vel);
+    assert!(pos.y < free_pos.y && vel.y < free_vel.y);
+}
+
+#[test]
+fn silent_bands_calm_the_turbulence() {
+    let forces = Forces {
+        turbulence: Turbulence {
+            modulation: 1.0,
+            ..turbulent().turbulence
+        },
+        ..Default::default()
+    };
+    let integrate = |forces: &Forces, level| {
+        let (mut pos, mut vel) = (Vec2::new(0.3, 0.2), Vec2::new(0.0, 1.0));
+
+        forces.integrate(G, &mut pos, &mut vel, 0.5, 3.0, level);
+        pos
+    };
+
+    assert_eq!(integrate(&forces, 0.0), integrate(&Forces::default(), 0.0));
+    assert_ne!(integrate(&forces, 1.0), integrate(&Forces::default(), 1.0));
+}
+
+#[test]
+fn integrated_particles_stay_in_the_frame() {
+    let particles = simulate(turbulent(), 60);
+
+    assert!(!particles.is_empty());
+
+    for particle in particles.iter().filter(|p| p.age < p.lifetime) {
+        assert!((-0.1..=1.1).contains(&particle.pos.x), "{:?}", particle);
+        assert!((0.0..=1.1).contains(&particle.pos.y), "{:?}", particle);
+    }
+
+    assert_eq!(particles, simulate(turbulent(), 60));
+}
+
+#[test]
+fn integrated_particles_outlive_the_apex() {
+    let forces = Forces {
+        wind: (0.01, 0.0).into(),
+        ..Default::default()
+    };
+
+    // away from the edges, which they could leave through sooner
+    let inside = |p: &Particle| (0.1..0.9).contains(&p.init_pos.x);
+
+    for particle in simulate(forces, 10).iter().filter(|p| inside(p)) {
+        let apex = Duration::from_secs_f32(particle.init_vel.y / G.y.abs());
+
+        assert!(particle.lifetime > apex, "{:?}", particle);
+    }
+}
@@ -0,0 +1,146 @@
+mod common;
+
+use std::{collections::HashMap, convert::TryInto};
+
+use chromaviz::prelude::*;
+use glam::Vec2;
+
+use common::{device, render, settings, FRAME, HEIGHT, WIDTH};
+
+const OP_NAME: u32 = 5;
+const OP_MEMBER_NAME: u32 = 6;
+const OP_MEMBER_DECORATE: u32 = 72;
+const DECORATION_OFFSET: u32 = 35;
+
+fn string(words: &[u32]) -> String {
+    let bytes: Vec<u8> = words.iter().flat_map(|w| w.to_le_bytes()).collect();
+    let len = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+
+    String::from_utf8(bytes[..len].to_vec()).unwrap()
+}
+
+// names and byte offsets of the members of the `Params` block, as compiled
+fn shader_params(spirv: &[u8]) -> Vec<(String, u32)> {
+    let words: Vec<u32> = spirv
+        .chunks(4)
+        .map(|w| u32::from_le_bytes(w.try_into().unwrap()))
+        .collect();
+    let mut block = None;
+    let mut names = HashMap::new();
+    let mut offsets = HashMap::new();
+    // past the header
+    let mut i = 5;
+
+    while i < words.len() {
+        let len = (words[i] >> 16) as usize;
+        let operands = &words[i + 1..i + len];
+
+        match words[i] & 0xffff {
+            OP_NAME if string(&operands[1..]) == "Params" => block = Some(operands[0]),
+            OP_MEMBER_NAME => {
+                names.insert((operands[0], operands[1]), string(&operands[2..]));
+            }
+            OP_MEMBER_DECORATE if operands[2] == DECORATION_OFFSET => {
+                offsets.insert((operands[0], operands[1]), operands[3]);
+            }
+            _ => (),
+        }
+
+        i += len;
+    }
+
+    let block = block.expect("no Params block");
+
+    (0..)
+        .map_while(|member| {
+            let name = names.get(&(block, member))?;
+
+            Some((name.clone(), offsets[&(block, member)]))
+        })
+        .collect()
+}
+
+// fields of the `#[repr(C)]` struct, as the shader would name them, and their byte offsets
+fn rust_params() -> Vec<(String, u32)> {
+    let source = include_str!("../src/chroma/gpu_particles.rs");
+    let start = source.find("struct Params {").unwrap();
+    let end = start + source[start..].find('}').unwrap();
+    let mut offset = 0;
+    let mut fields = Vec::new();
+
+    for line in source[start..end].lines().skip(1) {
+        let (name, ty) = line.trim().trim_end_matches(',').split_once(": ").unwrap();
+        let size = match ty {
+            "f32" | "u32" => 4,
+            "[f32; 2]" => 8,
+            _ => panic!("unexpected type {} in Params", ty),
+        };
+
+        if !name.starts_with('_') {
+            let camel: String = name
+                .split('_')
+                .map(|word| word[..1].to_uppercase() + &word[1..])
+                .collect();
+
+            fields.push((format!("u_{}", camel), offset));
+        }
+
+        offset += size;
+    }
+
+    fields
+}
+
+#[test]
+fn params_match_the_shaders() {
+    let rust = rust_params();
+
+    for spirv in &[
+        &include_bytes!("../src/chroma/shaders/particle_spawn.comp.spv")[..],
+        &include_bytes!("../src/chroma/shaders/particle_update.comp.spv")[..],
+    ] {
+        assert_eq!(shader_params(spirv), rust);
+    }
+}
+
+// only the first `capacity` particles fit, since none dies during the run
+fn dropped(device: &wgpu::Device, queue: &wgpu::Queue, backend: ParticleBackend) -> u64 {
+    let mut settings = settings(backend);
+    let particles = &mut settings.emitters[0].particles;
+
+    particles.capacity = 64;
+    particles.overflow = OverflowPolicy::DropNewest;
+    particles.gravity = Vec2::new(0.0, -0.01);
+
+    let format = wgpu::TextureFormat::Rgba8UnormSrgb;
+    let target = OffscreenTarget::new(device, WIDTH, HEIGHT, format);
+    let mut renderer = Chroma::new(device, WIDTH, HEIGHT, format, settings).unwrap();
+
+    for _ in 0..30 {
+        renderer.update(FRAME, &[0.5; 8]);
+        queue.submit(renderer.render(device, &target.view));
+    }
+
+    renderer.dropped_particles(device)
+}
+
+#[test]
+#[ignore]
+fn gpu_backend_matches_cpu() {
+    let (device, queue) = device();
+    let cpu = dropped(&device, &queue, ParticleBackend::Cpu);
+
+    assert!(cpu > 0);
+    assert_eq!(dropped(&device, &queue, ParticleBackend::Gpu), cpu);
+}
+
+#[test]
+#[ignore]
+fn gpu_backend_is_seeded() {
+    let (device, queue) = device();
+
+    assert_eq!(
+        render(&device, &queue, settings(ParticleBackend::Gpu)),
+        render(&device, &queue, settings(ParticleBackend::Gpu))
+    );
+}
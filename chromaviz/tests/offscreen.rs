@@ -0,0 +1,62 @@
+mod common;
+
+use std::fs::File;
+
+use chromaviz::{offscreen::save_png, prelude::*};
+
+use common::{device, render, settings, HEIGHT, WIDTH};
+
+#[test]
+fn png_round_trip() {
+    let path = std::env::temp_dir().join(format!("chromaviz-{}.png", std::process::id()));
+    let pixels: Vec<u8> = (0..4 * 3 * 4).map(|i| i as u8 * 5).collect();
+
+    save_png(&path, 4, 3, &pixels).unwrap();
+
+    let decoder = png::Decoder::new(File::open(&path).unwrap());
+    let (info, mut reader) = decoder.read_info().unwrap();
+    let mut decoded = vec![0; info.buffer_size()];
+    reader.next_frame(&mut decoded).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!((info.width, info.height), (4, 3));
+    assert_eq!(info.color_type, png::ColorType::RGBA);
+    assert_eq!(decoded, pixels);
+}
+
+#[test]
+#[ignore]
+fn renders_offscreen() {
+    let (device, queue) = device();
+
+    let pixels = render(&device, &queue, settings(ParticleBackend::Cpu));
+
+    assert_eq!(pixels.len(), (WIDTH * HEIGHT * 4) as usize);
+    assert!(pixels.chunks(4).any(|p| p[..3] != [0, 0, 0]));
+
+    // same seed, same frames
+    assert_eq!(
+        pixels,
+        render(&device, &queue, settings(ParticleBackend::Cpu))
+    );
+}
+
+#[test]
+fn y4m_frames() {
+    let mut writer = Y4mWriter::new(Vec::new(), 2, 1, 60).unwrap();
+
+    writer.write(&[255, 255, 255, 255, 0, 0, 0, 255]).unwrap();
+    writer.write(&[255, 0, 0, 255, 0, 0, 255, 255]).unwrap();
+
+    let out = writer.into_inner();
+    let header = b"YUV4MPEG2 W2 H1 F60:1 Ip A1:1 C444\n";
+
+    assert!(out.starts_with(header));
+
+    let frames: Vec<&[u8]> = out[header.len()..].chunks(6 + 6).collect();
+
+    assert_eq!(frames.len(), 2);
+    assert_eq!(frames[0], b"FRAME\n\xeb\x10\x80\x80\x80\x80");
+    // red has the highest V, blue the highest U
+    assert!(frames[1][8] < frames[1][9] && frames[1][10] > frames[1][11]);
+}
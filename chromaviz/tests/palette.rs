@@ -0,0 +1,80 @@
+use std::time::Duration;
+
+use chromaviz::chroma::{ColorStop, Palette, PaletteKey, Particle};
+
+fn gradient() -> Palette {
+    Palette {
+        key: PaletteKey::Frequency,
+        stops: vec![
+            ColorStop {
+                position: 0.25,
+                color: [1.0, 0.0, 0.0],
+            },
+            ColorStop {
+                position: 0.75,
+                color: [0.0, 0.0, 1.0],
+            },
+        ],
+    }
+}
+
+#[test]
+fn interpolates_between_stops() {
+    let palette = gradient();
+
+    assert_eq!(palette.sample(0.0), [1.0, 0.0, 0.0]);
+    assert_eq!(palette.sample(0.5), [0.5, 0.0, 0.5]);
+    assert_eq!(palette.sample(1.0), [0.0, 0.0, 1.0]);
+    assert_eq!(palette.sample(-3.0), palette.sample(0.0));
+}
+
+#[test]
+fn rainbow_goes_around_the_hue_circle() {
+    let palette = Palette::rainbow();
+
+    assert_eq!(palette.sample(0.0), [1.0, 0.0, 0.0]);
+    assert_eq!(palette.sample(1.0 / 3.0), [0.0, 1.0, 0.0]);
+    assert_eq!(palette.sample(1.0), [1.0, 0.0, 0.0]);
+}
+
+#[test]
+fn bakes_a_texel_per_step() {
+    let texels = gradient().bake();
+
+    assert_eq!(texels.len(), 256 * 4);
+    assert_eq!(texels[..4], [255, 0, 0, 255]);
+    assert_eq!(texels[texels.len() - 4..], [0, 0, 255, 255]);
+}
+
+#[test]
+fn keys() {
+    let g = (0.0, -4.0).into();
+    // launched straight up to a height of 0.25, half way there
+    let particle = Particle {
+        init_pos: (0.3, 0.0).into(),
+        init_vel: (0.0, 2.0_f32.sqrt()).into(),
+        pos: (0.3, 0.1875).into(),
+        vel: (0.0, 0.5_f32.sqrt()).into(),
+        age: Duration::from_secs_f32(0.5_f32.sqrt() / 4.0),
+        lifetime: Duration::from_secs_f32(0.5_f32.sqrt() / 2.0),
+        hue: 0.3,
+        size: 4.0,
+        sprite: 0,
+        rotation: 0.0,
+        spin: 0.0,
+    };
+    let key = |key| particle.palette_key(key, g);
+
+    assert_eq!(key(PaletteKey::Frequency), 0.3);
+    assert!((key(PaletteKey::Age) - 0.5).abs() < 1e-3);
+    assert!((key(PaletteKey::Velocity) - 0.25).abs() < 1e-3);
+    assert!((key(PaletteKey::Energy) - 0.25).abs() < 1e-3);
+
+    // launched at an angle by the same level
+    let slanted = Particle {
+        init_vel: (1.0, 1.0).into(),
+        ..particle
+    };
+
+    assert!((slanted.palette_key(PaletteKey::Energy, g) - 0.25).abs() < 1e-3);
+}
@@ -0,0 +1,198 @@
+use std::time::Duration;
+
+use chromaviz::chroma::{Emitter, OverflowPolicy, Particle, ParticleSettings, ParticleSystem};
+
+const FRAME: Duration = Duration::from_millis(16);
+
+fn settings() -> ParticleSettings {
+    ParticleSettings {
+        frequencies: 8,
+        ..Default::default()
+    }
+}
+
+// the default line across the floor
+fn emitter(particles: ParticleSettings) -> Emitter {
+    Emitter {
+        particles,
+        ..Default::default()
+    }
+}
+
+fn simulate(seed: u64, frames: usize) -> Vec<Particle> {
+    let mut system = ParticleSystem::new(Some(seed));
+
+    for _ in 0..frames {
+        system.update(FRAME, &[0.5; 8], &emitter(settings()));
+    }
+
+    system.particles().cloned().collect()
+}
+
+#[test]
+fn same_seed_same_particles() {
+    assert_eq!(simulate(42, 30), simulate(42, 30));
+    assert_ne!(simulate(42, 30), simulate(43, 30));
+}
+
+#[test]
+fn emits_at_the_configured_rate() {
+    let mut system = ParticleSystem::new(Some(0));
+
+    system.update(FRAME, &[0.5; 8], &emitter(settings()));
+    assert_eq!(system.count(), 32);
+
+    system.update(FRAME, &[0.5; 8], &emitter(settings()));
+    assert_eq!(system.count(), 64);
+}
+
+#[test]
+fn particles_reach_the_band_level() {
+    let g = settings().gravity;
+
+    for particle in simulate(7, 10) {
+        // particles die at the top of their trajectory, whose height is v²/2g
+        let apex = particle.init_vel.y.powi(2) / (2.0 * g.y.abs());
+
+        assert!((-0.2..=1.2).contains(&particle.init_pos.x));
+        assert!((4.0..6.0).contains(&particle.size));
+        // with a little velocity spread
+        assert!((apex - 0.5).abs() < 0.1, "{:?}", particle);
+        assert!(particle.pos.y <= apex + 1e-4, "{:?}", particle);
+    }
+}
+
+#[test]
+fn particles_die() {
+    let mut system = ParticleSystem::new(Some(0));
+
+    system.update(FRAME, &[0.5; 8], &emitter(settings()));
+
+    // lifetimes are around 0.5s, well under a second
+    for _ in 0..60 {
+        system.update(
+            FRAME,
+            &[0.0; 8],
+            &emitter(ParticleSettings {
+                particles_per_second: 1,
+                ..settings()
+            }),
+        );
+    }
+
+    assert!(system.particles().all(|p| p.age < Duration::from_secs(1)));
+    assert!(system.count() <= 1);
+}
+
+fn overflowing(overflow: OverflowPolicy) -> ParticleSettings {
+    ParticleSettings {
+        capacity: 10,
+        overflow,
+        ..settings()
+    }
+}
+
+#[test]
+fn drop_newest_keeps_the_oldest() {
+    let mut full = ParticleSystem::new(Some(0));
+    let mut unlimited = ParticleSystem::new(Some(0));
+
+    full.update(
+        FRAME,
+        &[0.5; 8],
+        &emitter(overflowing(OverflowPolicy::DropNewest)),
+    );
+    unlimited.update(FRAME, &[0.5; 8], &emitter(settings()));
+
+    assert_eq!(full.count(), 10);
+    assert_eq!(full.dropped(), 22);
+    assert!(full.particles().eq(unlimited.particles().take(10)));
+}
+
+#[test]
+fn recycle_oldest_keeps_the_newest() {
+    let mut full = ParticleSystem::new(Some(0));
+    let mut unlimited = ParticleSystem::new(Some(0));
+
+    full.update(
+        FRAME,
+        &[0.5; 8],
+        &emitter(overflowing(OverflowPolicy::RecycleOldest)),
+    );
+    unlimited.update(FRAME, &[0.5; 8], &emitter(settings()));
+
+    assert_eq!(full.count(), 10);
+    assert_eq!(full.dropped(), 22);
+    assert!(full.particles().eq(unlimited.particles().skip(22)));
+}
+
+#[test]
+fn scale_emission_slows_down() {
+    let scaled = emitter(ParticleSettings {
+        capacity: 100,
+        ..overflowing(OverflowPolicy::ScaleEmission)
+    });
+    let mut system = ParticleSystem::new(Some(0));
+
+    system.update(FRAME, &[0.5; 8], &scaled);
+
+    // lifetimes are around 0.5s, so 2000 particles per second would need room for 1000
+    assert_eq!(system.count(), 3);
+
+    for _ in 0..120 {
+        system.update(FRAME, &[0.5; 8], &scaled);
+    }
+
+    assert!(system.count() <= 100);
+    assert!(system.dropped() < 100, "{}", system.dropped());
+}
+
+#[test]
+fn shrinking_drops_the_oldest() {
+    let mut system = ParticleSystem::new(Some(0));
+
+    system.update(FRAME, &[0.5; 8], &emitter(settings()));
+    system.update(FRAME, &[0.5; 8], &emitter(settings()));
+
+    let newest: Vec<_> = system.particles().skip(54).cloned().collect();
+
+    system.update(
+        Duration::from_secs(0),
+        &[0.5; 8],
+        &emitter(ParticleSettings {
+            capacity: 10,
+            ..settings()
+        }),
+    );
+
+    assert_eq!(system.dropped(), 54);
+    assert!(system.particles().eq(newest.iter()));
+}
+
+#[test]
+fn rotation_and_spin() {
+    let spinning = ParticleSettings {
+        rotation_range: 90.0..180.0,
+        spin_range: -45.0..45.0,
+        ..settings()
+    };
+    let mut system = ParticleSystem::new(Some(0));
+
+    system.update(FRAME, &[0.5; 8], &emitter(spinning));
+
+    let (quarter, half) = (90f32.to_radians(), 180f32.to_radians());
+
+    for particle in system.particles() {
+        assert!((quarter..half).contains(&particle.rotation));
+        assert!(particle.spin.abs() < 45f32.to_radians());
+
+        let spun = particle.spin * particle.age.as_secs_f32();
+        assert!((particle.angle() - particle.rotation - spun).abs() < 1e-6);
+    }
+
+    // a fixed rotation and no spin by default
+    let mut system = ParticleSystem::new(Some(0));
+
+    system.update(FRAME, &[0.5; 8], &emitter(settings()));
+    assert!(system.particles().all(|p| p.angle() == 0.0));
+}
@@ -0,0 +1,241 @@
+#![cfg(feature = "serde")]
+
+use chromaviz::{
+    prelude::*,
+    preset::{self, PresetError},
+};
+use std::path::PathBuf;
+
+#[test]
+fn default_preset_matches_defaults() {
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/../presets/default.toml");
+
+    assert_eq!(preset::load(path).unwrap(), ChromaSettings::default());
+}
+
+#[test]
+fn missing_fields_keep_defaults() {
+    let settings = preset::from_toml(
+        "decay = 0.5\n[[emitters]]\n[emitters.particles]\ngravity = [1.0, -2.0]\n",
+    )
+    .unwrap();
+
+    assert_eq!(settings.decay, 0.5);
+    assert_eq!(settings.emitters[0].particles.gravity, (1.0, -2.0).into());
+    assert_eq!(settings.emitters[0].particles.size_range, 4.0..6.0);
+    assert_eq!(settings.emitters[0].particles.frequencies, 32);
+}
+
+#[test]
+fn json_presets() {
+    let settings = preset::from_json(
+        r#"{ "emitters": [{ "particles": { "size_range": { "start": 1.0, "end": 2.0 }, "seed": 7 } }] }"#,
+    )
+    .unwrap();
+
+    assert_eq!(settings.decay, 0.95);
+    assert_eq!(settings.emitters[0].particles.size_range, 1.0..2.0);
+    assert_eq!(settings.emitters[0].particles.seed, Some(7));
+}
+
+#[test]
+fn palettes() {
+    let settings = preset::from_toml(
+        r#"
+            [[emitters]]
+            [emitters.particles.palette]
+            key = "energy"
+            stops = [
+                { position = 0.0, color = [0.0, 0.0, 0.2] },
+                { position = 1.0, color = [1.0, 0.8, 0.0] },
+            ]
+        "#,
+    )
+    .unwrap();
+
+    assert_eq!(
+        settings.emitters[0].particles.palette.key,
+        PaletteKey::Energy
+    );
+    assert_eq!(settings.emitters[0].particles.palette.stops.len(), 2);
+    assert_eq!(
+        settings.emitters[0].particles.palette.sample(1.0),
+        [1.0, 0.8, 0.0]
+    );
+}
+
+#[test]
+fn sprites() {
+    let settings = preset::from_toml(
+        "[[emitters]]\n[emitters.particles]\nsprites = [\"star\", { image = \"logo.png\" }]",
+    )
+    .unwrap();
+
+    assert_eq!(
+        settings.emitters[0].particles.sprites,
+        [Sprite::Star, Sprite::Image("logo.png".into())]
+    );
+}
+
+#[test]
+fn emitters() {
+    let settings = preset::from_toml(
+        r#"
+            [[emitters]]
+
+            [[emitters]]
+            mirrored = true
+            frequency_range = { start = 0.0, end = 0.5 }
+
+            [[emitters]]
+            shape = { circle = { radius = 0.2 } }
+            position = [0.5, 0.5]
+            [emitters.particles]
+            frequencies = 64
+
+            [[emitters]]
+            layout = { polar = { center = [0.5, 0.5], radius = 0.2 } }
+        "#,
+    )
+    .unwrap();
+
+    assert_eq!(settings.emitters.len(), 4);
+    assert_eq!(settings.emitters[0], Emitter::default());
+    assert!(settings.emitters[1].mirrored);
+    assert_eq!(
+        settings.emitters[2].shape,
+        EmitterShape::Circle { radius: 0.2 }
+    );
+    assert_eq!(
+        settings.emitters[3].layout,
+        Layout::Polar {
+            center: (0.5, 0.5).into(),
+            radius: 0.2
+        }
+    );
+    assert_eq!(settings.bands(), 64);
+}
+
+#[test]
+fn blur() {
+    let settings = preset::from_toml("[blur]\nkernel = \"dual_kawase\"\niterations = 4\n").unwrap();
+
+    assert_eq!(
+        settings.blur,
+        Blur {
+            kernel: BlurKernel::DualKawase,
+            iterations: 4,
+            ..Default::default()
+        }
+    );
+}
+
+#[test]
+fn feedback() {
+    let settings = preset::from_toml(
+        "[feedback]\nzoom = 1.02\n[feedback.audio]\nfrequency_range = { start = 0.0, end = 0.25 }\nrotation = 5.0\n",
+    )
+    .unwrap();
+
+    assert_eq!(settings.feedback.zoom, 1.02);
+    assert_eq!(settings.feedback.audio.frequency_range, 0.0..0.25);
+    assert_eq!(settings.feedback.audio.rotation, 5.0);
+    assert_eq!(settings.feedback.warp_scale, Feedback::default().warp_scale);
+}
+
+#[test]
+fn effects() {
+    let settings = preset::from_toml(
+        r#"
+            effects = [
+                { vignette = { strength = 0.8 } },
+                { enabled = false, kaleidoscope = { segments = 3 } },
+            ]
+
+            [[emitters]]
+        "#,
+    )
+    .unwrap();
+
+    assert_eq!(
+        settings.effects,
+        [
+            PostEffect::from(Effect::Vignette(Vignette {
+                strength: 0.8,
+                ..Default::default()
+            })),
+            PostEffect {
+                enabled: false,
+                effect: Effect::Kaleidoscope(Kaleidoscope {
+                    segments: 3,
+                    ..Default::default()
+                }),
+            },
+        ]
+    );
+    assert!(preset::from_toml("effects = [{ bloom = {} }]").is_err());
+}
+
+#[test]
+fn shaders() {
+    let settings = preset::from_toml(
+        r#"
+            shaders = [
+                { path = "ripple.frag" },
+                { path = "off.frag", enabled = false },
+            ]
+
+            [[emitters]]
+        "#,
+    )
+    .unwrap();
+
+    assert_eq!(
+        settings.shaders,
+        [
+            CustomShader::from(PathBuf::from("ripple.frag")),
+            CustomShader {
+                path: "off.frag".into(),
+                enabled: false,
+            },
+        ]
+    );
+    assert!(preset::from_toml("shaders = [{ enabled = true }]").is_err());
+}
+
+#[test]
+fn tonemapping() {
+    let settings =
+        preset::from_toml("[tonemapping]\noperator = \"aces\"\nexposure = 2.0\n").unwrap();
+
+    assert_eq!(
+        settings.tonemapping,
+        Tonemapping {
+            operator: Tonemap::Aces,
+            exposure: 2.0,
+        }
+    );
+    assert_eq!(
+        preset::from_toml("[tonemapping]\noperator = \"reinhard\"")
+            .unwrap()
+            .tonemapping
+            .exposure,
+        1.0
+    );
+}
+
+#[test]
+fn invalid_presets() {
+    assert!(matches!(
+        preset::from_toml("decay = \"slow\""),
+        Err(PresetError::Toml(_))
+    ));
+    assert!(matches!(
+        preset::from_toml("[[emitters]]\n[emitters.particles]\nfrequencies = 1"),
+        Err(PresetError::Invalid(SettingsError::TooFewFrequencies(1)))
+    ));
+    assert!(matches!(
+        preset::load("preset.yaml"),
+        Err(PresetError::UnknownFormat(_))
+    ));
+}
@@ -0,0 +1,206 @@
+use chromaviz::prelude::*;
+
+fn particles(f: impl FnOnce(&mut ParticleSettings)) -> Result<(), SettingsError> {
+    let mut settings = ChromaSettings::default();
+
+    f(&mut settings.emitters[0].particles);
+    settings.validate()
+}
+
+#[test]
+fn defaults_are_valid() {
+    assert_eq!(ChromaSettings::default().validate(), Ok(()));
+}
+
+#[test]
+fn decay() {
+    for decay in &[-0.1, 1.5] {
+        let settings = ChromaSettings {
+            decay: *decay,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            settings.validate(),
+            Err(SettingsError::DecayOutOfRange(*decay))
+        );
+    }
+
+    let settings = ChromaSettings {
+        decay: f64::NAN,
+        ..Default::default()
+    };
+
+    assert_eq!(settings.validate(), Err(SettingsError::NotFinite("decay")));
+}
+
+#[test]
+fn exposure() {
+    let mut settings = ChromaSettings::default();
+
+    settings.tonemapping.exposure = -1.0;
+    assert_eq!(
+        settings.validate(),
+        Err(SettingsError::NegativeExposure(-1.0))
+    );
+
+    settings.tonemapping.exposure = f32::INFINITY;
+    assert_eq!(
+        settings.validate(),
+        Err(SettingsError::NotFinite("tonemapping.exposure"))
+    );
+
+    settings.tonemapping.exposure = 0.0;
+    assert_eq!(settings.validate(), Ok(()));
+}
+
+#[test]
+fn blur() {
+    for blur in &[
+        Blur {
+            radius: -1.0,
+            ..Default::default()
+        },
+        Blur {
+            iterations: 0,
+            ..Default::default()
+        },
+        Blur {
+            iterations: 9,
+            ..Default::default()
+        },
+        Blur {
+            downsample: 0,
+            ..Default::default()
+        },
+    ] {
+        let settings = ChromaSettings {
+            blur: blur.clone(),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            settings.validate(),
+            Err(SettingsError::InvalidBlur(blur.clone()))
+        );
+    }
+
+    let settings = ChromaSettings {
+        blur: Blur {
+            kernel: BlurKernel::DualKawase,
+            radius: 0.0,
+            iterations: 8,
+            downsample: 16,
+        },
+        ..Default::default()
+    };
+
+    assert_eq!(settings.validate(), Ok(()));
+}
+
+#[test]
+fn frequencies() {
+    assert_eq!(
+        particles(|p| p.frequencies = 1),
+        Err(SettingsError::TooFewFrequencies(1))
+    );
+    assert_eq!(particles(|p| p.frequencies = 2), Ok(()));
+}
+
+#[test]
+fn emission_rate() {
+    assert_eq!(
+        particles(|p| p.particles_per_second = 0),
+        Err(SettingsError::EmissionRate(0))
+    );
+    assert_eq!(
+        particles(|p| p.particles_per_second = u64::MAX),
+        Err(SettingsError::EmissionRate(u64::MAX))
+    );
+}
+
+#[test]
+fn gravity() {
+    assert_eq!(
+        particles(|p| p.gravity = (0.0, 1.0).into()),
+        Err(SettingsError::UpwardGravity(1.0))
+    );
+    assert_eq!(
+        particles(|p| p.gravity = (f32::INFINITY, -4.0).into()),
+        Err(SettingsError::NotFinite("gravity"))
+    );
+}
+
+#[test]
+fn spreads_and_sizes() {
+    assert_eq!(
+        particles(|p| p.angular_spread = -1.0),
+        Err(SettingsError::NegativeSpread("angular_spread"))
+    );
+    assert_eq!(
+        particles(|p| p.size_range = 2.0..2.0),
+        Err(SettingsError::InvalidSizeRange(2.0..2.0))
+    );
+    assert_eq!(
+        particles(|p| p.size_range = -1.0..2.0),
+        Err(SettingsError::InvalidSizeRange(-1.0..2.0))
+    );
+}
+
+#[test]
+fn capacity() {
+    assert_eq!(
+        particles(|p| p.capacity = 0),
+        Err(SettingsError::InvalidCapacity(0))
+    );
+    assert_eq!(
+        particles(|p| p.capacity = usize::MAX),
+        Err(SettingsError::InvalidCapacity(usize::MAX))
+    );
+}
+
+#[test]
+fn palette() {
+    assert_eq!(
+        particles(|p| p.palette.stops.clear()),
+        Err(SettingsError::EmptyPalette)
+    );
+    assert_eq!(
+        particles(|p| p.palette.stops.swap(1, 2)),
+        Err(SettingsError::InvalidColorStop(2))
+    );
+    assert_eq!(
+        particles(|p| p.palette.stops[3].color[0] = 2.0),
+        Err(SettingsError::InvalidColorStop(3))
+    );
+}
+
+#[test]
+fn rotation_ranges() {
+    assert_eq!(
+        particles(|p| p.spin_range = 10.0..-10.0),
+        Err(SettingsError::ReversedRange("spin_range"))
+    );
+    assert_eq!(
+        particles(|p| p.rotation_range = 0.0..f32::INFINITY),
+        Err(SettingsError::NotFinite("rotation_range"))
+    );
+    assert_eq!(particles(|p| p.rotation_range = 45.0..45.0), Ok(()));
+}
+
+#[test]
+fn forces() {
+    assert_eq!(
+        particles(|p| p.forces.quadratic_drag = -1.0),
+        Err(SettingsError::NegativeForce("forces.quadratic_drag"))
+    );
+    assert_eq!(
+        particles(|p| p.forces.wind.x = f32::NAN),
+        Err(SettingsError::NotFinite("forces.wind"))
+    );
+    assert_eq!(
+        particles(|p| p.forces.turbulence.modulation = 1.5),
+        Err(SettingsError::TurbulenceModulation(1.5))
+    );
+    assert_eq!(particles(|p| p.forces.wind = (-0.5, 0.0).into()), Ok(()));
+}
@@ -0,0 +1,100 @@
+use std::time::Duration;
+
+use chromaviz::{
+    chroma::{Emitter, ParticleSettings, ParticleSystem, Sprite},
+    offscreen::save_png,
+    prelude::*,
+};
+
+const SIZE: usize = 64;
+
+fn alpha(texels: &[u8], x: usize, y: usize) -> u8 {
+    texels[(y * SIZE + x) * 4 + 3]
+}
+
+#[test]
+fn shapes() {
+    for shape in &[
+        Sprite::Circle,
+        Sprite::Glow,
+        Sprite::Ring,
+        Sprite::Star,
+        Sprite::Square,
+    ] {
+        let texels = shape.load().unwrap();
+
+        assert_eq!(texels.len(), SIZE * SIZE * 4);
+        assert_eq!(alpha(&texels, 0, 0), 0, "{:?}", shape);
+
+        let center = alpha(&texels, SIZE / 2, SIZE / 2);
+
+        match shape {
+            Sprite::Ring => assert_eq!(center, 0),
+            _ => assert!(center > 200, "{:?}", shape),
+        }
+    }
+
+    // stars point up, which is the first row of the texture
+    let star = Sprite::Star.load().unwrap();
+    assert!(alpha(&star, SIZE / 2, 14) > 0);
+    assert_eq!(alpha(&star, SIZE / 2, SIZE - 14), 0);
+}
+
+#[test]
+fn images() {
+    let path = std::env::temp_dir().join(format!("chromaviz-sprite-{}.png", std::process::id()));
+    // opaque red on the left half, transparent on the right
+    let pixels: Vec<u8> = (0..8 * 8)
+        .flat_map(|i| if i % 8 < 4 { [255, 0, 0, 255] } else { [0; 4] })
+        .collect();
+
+    save_png(&path, 8, 8, &pixels).unwrap();
+
+    let texels = Sprite::Image(path.clone()).load().unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(texels.len(), SIZE * SIZE * 4);
+    assert_eq!(texels[..4], [255, 0, 0, 255]);
+    assert_eq!(alpha(&texels, SIZE - 1, SIZE - 1), 0);
+
+    assert!(matches!(
+        Sprite::Image("missing.png".into()).load(),
+        Err(SettingsError::SpriteImage { .. })
+    ));
+}
+
+#[test]
+fn random_sprites_and_rotations() {
+    let emitter = Emitter {
+        particles: ParticleSettings {
+            frequencies: 8,
+            sprites: vec![Sprite::Circle, Sprite::Star, Sprite::Ring],
+            rotation_range: 0.0..360.0,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let mut system = ParticleSystem::new(Some(0));
+
+    system.update(Duration::from_millis(16), &[0.5; 8], &emitter);
+
+    for sprite in 0..3 {
+        assert!(system.particles().any(|p| p.sprite == sprite));
+    }
+
+    assert!(system
+        .particles()
+        .all(|p| (0.0..std::f32::consts::TAU).contains(&p.rotation)));
+    assert!(system.particles().any(|p| p.rotation > 1.0));
+}
+
+#[test]
+fn sprite_count() {
+    let mut settings = ChromaSettings::default();
+
+    settings.emitters[0].particles.sprites.clear();
+    assert_eq!(settings.validate(), Err(SettingsError::SpriteCount(0)));
+
+    settings.emitters[0].particles.sprites = vec![Sprite::Glow; 257];
+    assert_eq!(settings.validate(), Err(SettingsError::SpriteCount(257)));
+}
@@ -0,0 +1,15 @@
+// ripples out from the middle with the bass, and tints the trails with the treble
+
+void main() {
+    float aspect = u_Resolution.x / u_Resolution.y;
+    vec2 offset = (v_TexCoord - 0.5) * vec2(aspect, 1.0);
+    float d = length(offset);
+    float bass = band(0.05);
+    float treble = band(0.8);
+
+    vec2 ripple = normalize(offset + 1e-5) * sin(d * 40.0 - u_Time * 6.0) * 0.01 * bass;
+    vec4 color = texture(sampler2D(t_Color, s_Color), v_TexCoord + ripple / vec2(aspect, 1.0));
+    vec4 trails = texture(sampler2D(t_Accumulator, s_Accumulator), v_TexCoord);
+
+    outColor = vec4(color.rgb + trails.rgb * vec3(0.2, 0.1, 0.4) * treble, color.a);
+}
@@ -0,0 +1,256 @@
+use std::{
+    fmt,
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+    process::{Child, Command, ExitStatus, Stdio},
+    time::Duration,
+};
+
+use {chromaplay::prelude::*, chromaviz::prelude::*, futures::executor::block_on};
+
+use crate::options::Format;
+
+const FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8UnormSrgb;
+
+#[derive(Debug)]
+pub enum Error {
+    NoAdapter,
+    Device(wgpu::RequestDeviceError),
+    Readback(wgpu::BufferAsyncError),
+    Settings(SettingsError),
+    Io(io::Error),
+    Encoder(ExitStatus),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::NoAdapter => write!(f, "no suitable graphics adapter"),
+            Error::Device(e) => write!(f, "couldn't open graphics device: {}", e),
+            Error::Readback(e) => write!(f, "couldn't read back frame: {}", e),
+            Error::Settings(e) => write!(f, "invalid settings: {}", e),
+            Error::Io(e) => write!(f, "{}", e),
+            Error::Encoder(status) => write!(f, "ffmpeg failed ({})", status),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<wgpu::RequestDeviceError> for Error {
+    fn from(e: wgpu::RequestDeviceError) -> Self {
+        Error::Device(e)
+    }
+}
+
+impl From<wgpu::BufferAsyncError> for Error {
+    fn from(e: wgpu::BufferAsyncError) -> Self {
+        Error::Readback(e)
+    }
+}
+
+impl From<SettingsError> for Error {
+    fn from(e: SettingsError) -> Self {
+        Error::Settings(e)
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+pub struct Export<'a> {
+    pub audio: &'a Path,
+    pub output: &'a Path,
+    pub format: Format,
+    pub fps: u32,
+    pub size: (u32, u32),
+}
+
+enum Sink {
+    // the encoder is the ffmpeg process reading the stream, if any
+    Y4m(Y4mWriter<Box<dyn Write>>, Option<Encoder>),
+    Png(PngSequence),
+}
+
+// an ffmpeg process, killed along with its truncated output if the export fails before `wait`
+struct Encoder {
+    child: Child,
+    output: PathBuf,
+    finished: bool,
+}
+
+impl Encoder {
+    // the stream must be closed first
+    fn wait(mut self) -> Result<(), Error> {
+        let status = self.child.wait()?;
+        self.finished = true;
+
+        if !status.success() {
+            return Err(Error::Encoder(status));
+        }
+
+        Ok(())
+    }
+}
+
+impl Drop for Encoder {
+    fn drop(&mut self) {
+        if !self.finished {
+            let _ = self.child.kill();
+            let _ = self.child.wait();
+            let _ = fs::remove_file(&self.output);
+        }
+    }
+}
+
+impl Sink {
+    fn open(export: &Export) -> Result<Self, Error> {
+        let (width, height) = export.size;
+
+        if let Format::Ffmpeg = export.format {
+            let mut child = Command::new("ffmpeg")
+                .args([
+                    "-y",
+                    "-loglevel",
+                    "error",
+                    "-f",
+                    "yuv4mpegpipe",
+                    "-i",
+                    "-",
+                    "-i",
+                ])
+                .arg(export.audio)
+                .args(["-c:v", "libx264", "-pix_fmt", "yuv420p", "-c:a", "aac"])
+                .arg(export.output)
+                .stdin(Stdio::piped())
+                .spawn()?;
+            let stdin: Box<dyn Write> = Box::new(BufWriter::new(child.stdin.take().unwrap()));
+            let encoder = Encoder {
+                child,
+                output: export.output.to_owned(),
+                finished: false,
+            };
+
+            return Ok(Sink::Y4m(
+                Y4mWriter::new(stdin, width, height, export.fps)?,
+                Some(encoder),
+            ));
+        }
+
+        fs::create_dir_all(export.output)?;
+        fs::copy(export.audio, export.output.join("audio.wav"))?;
+
+        Ok(match export.format {
+            Format::Png => Sink::Png(PngSequence::new(export.output)?),
+            _ => {
+                let file: Box<dyn Write> = Box::new(BufWriter::new(File::create(
+                    export.output.join("video.y4m"),
+                )?));
+
+                Sink::Y4m(Y4mWriter::new(file, width, height, export.fps)?, None)
+            }
+        })
+    }
+
+    fn write(&mut self, width: u32, height: u32, rgba: &[u8]) -> io::Result<()> {
+        match self {
+            Sink::Y4m(writer, _) => writer.write(rgba),
+            Sink::Png(sequence) => sequence.write(width, height, rgba).map(|_| ()),
+        }
+    }
+
+    fn finish(self) -> Result<(), Error> {
+        if let Sink::Y4m(writer, encoder) = self {
+            // dropping the writer closes stdin, which lets ffmpeg finish the file
+            writer.into_inner().flush()?;
+
+            if let Some(encoder) = encoder {
+                encoder.wait()?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Renders `buffer` frame by frame, as fast as the GPU allows.
+///
+/// Every frame advances the simulation by exactly `1 / fps` and sees the spectrum at the matching
+/// sample, so the output doesn't depend on how long rendering takes.
+pub fn export(
+    buffer: &AudioBuffer,
+    settings: ChromaSettings,
+    export: &Export,
+) -> Result<(), Error> {
+    let (width, height) = export.size;
+    let instance = wgpu::Instance::new(wgpu::BackendBit::PRIMARY);
+
+    let adapter = block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
+        power_preference: wgpu::PowerPreference::HighPerformance,
+        compatible_surface: None,
+    }))
+    .ok_or(Error::NoAdapter)?;
+
+    let (device, queue) = block_on(adapter.request_device(
+        &wgpu::DeviceDescriptor {
+            features: wgpu::Features::empty(),
+            limits: wgpu::Limits::default(),
+            shader_validation: true,
+        },
+        None,
+    ))?;
+
+    let bands = settings.bands();
+    let target = OffscreenTarget::new(&device, width, height, FORMAT);
+    let mut renderer = Chroma::new(&device, width, height, FORMAT, settings)?;
+
+    queue.submit(renderer.resize(&device, width, height));
+
+    // a hop of 1 analyzes at the exact sample instead of snapping to a coarser grid
+    let mut analyzer = SpectrumAnalyzer::new(AnalyzerSettings {
+        bands,
+        hop: 1,
+        ..Default::default()
+    });
+    let mut levels = LevelProcessor::new(LevelSettings::default(), bands);
+
+    let rate = buffer.sample_rate() as u64;
+    let fps = export.fps as u64;
+    let frame_count = (buffer.frames() as u64 * fps + rate - 1) / rate;
+    let delta = Duration::from_secs(1) / export.fps;
+    let mut sink = Sink::open(export)?;
+
+    for frame in 0..frame_count {
+        let sample = (frame * rate / fps) as usize;
+        let freq_data = levels.process(delta, analyzer.analyze(buffer, sample));
+
+        renderer.update(delta, freq_data);
+        queue.submit(renderer.render(&device, &target.view));
+        queue.submit(target.copy(&device));
+
+        let pixels = block_on(target.read(&device))?;
+        sink.write(width, height, &pixels)?;
+    }
+
+    sink.finish()?;
+    println!(
+        "exported {} frames to {}",
+        frame_count,
+        export.output.display()
+    );
+
+    let dropped = renderer.dropped_particles(&device);
+
+    if dropped > 0 {
+        println!(
+            "{} particles were dropped, consider raising the capacity",
+            dropped
+        );
+    }
+
+    Ok(())
+}
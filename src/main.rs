@@ -1,21 +1,106 @@
-use std::{env::args, path::PathBuf};
+mod export;
+mod options;
+mod watch;
+
+use std::{env::args, process::exit};
 
 use native_dialog::{Dialog, OpenSingleFile};
 
 use {
-    chromaviz::prelude::*,
+    chromaplay::{player::PlaybackState, prelude::*},
+    chromaviz::{prelude::*, preset},
+    export::Export,
     futures::executor::block_on,
-    std::time::{Duration, Instant},
+    options::{Options, USAGE},
+    std::{
+        path::Path,
+        time::{Duration, Instant},
+    },
+    watch::FileWatcher,
     winit::{
         event::{Event, WindowEvent},
         event_loop::{ControlFlow, EventLoop},
     },
 };
 
-fn viz() {
+struct Audio {
+    player: Player,
+    analyzer: SpectrumAnalyzer,
+    levels: LevelProcessor,
+    last_position: Duration,
+}
+
+impl Audio {
+    fn new(buffer: AudioBuffer, bands: usize) -> Self {
+        let player = Player::new(buffer.clone()).unwrap_or_else(|e| {
+            eprintln!("{}, playing silently", e);
+            Player::with_null_output(buffer)
+        });
+
+        Self {
+            player,
+            analyzer: SpectrumAnalyzer::new(AnalyzerSettings {
+                bands,
+                ..Default::default()
+            }),
+            levels: LevelProcessor::new(LevelSettings::default(), bands),
+            last_position: Duration::from_secs(0),
+        }
+    }
+
+    // the analysis restarts from scratch when the number of bands changes
+    fn set_bands(&mut self, bands: usize) {
+        if self.analyzer.settings().bands != bands {
+            self.analyzer = SpectrumAnalyzer::new(AnalyzerSettings {
+                bands,
+                ..self.analyzer.settings().clone()
+            });
+            self.levels = LevelProcessor::new(self.levels.settings().clone(), bands);
+        }
+    }
+
+    // advances along the playback clock, returning how far it moved and the band levels there
+    fn update(&mut self) -> (Duration, &[f32]) {
+        let position = self.player.position();
+        let delta = position.checked_sub(self.last_position).unwrap_or_default();
+        let bands = self.analyzer.analyze_at(self.player.buffer(), position);
+
+        self.last_position = position;
+
+        (delta, self.levels.process(delta, bands))
+    }
+}
+
+const TITLE: &str = "ChromaViz";
+
+// the preset, or the defaults, with the seed from the command line on top
+fn load_settings(preset: Option<&Path>, seed: Option<u64>) -> Result<ChromaSettings, PresetError> {
+    let mut settings = match preset {
+        Some(path) => preset::load(path)?,
+        None => ChromaSettings::default(),
+    };
+
+    // each emitter gets a seed of its own, so that they don't emit in lockstep
+    if let Some(seed) = seed {
+        for (i, emitter) in settings.emitters.iter_mut().enumerate() {
+            emitter.particles.seed = Some(seed.wrapping_add(i as u64));
+        }
+    }
+
+    Ok(settings)
+}
+
+fn load_settings_or_exit(options: &Options) -> ChromaSettings {
+    load_settings(options.preset.as_deref(), options.seed).unwrap_or_else(|e| {
+        eprintln!("couldn't load preset: {}", e);
+        exit(1);
+    })
+}
+
+fn viz(buffer: AudioBuffer, options: Options, settings: ChromaSettings) {
     let event_loop = EventLoop::new();
     let window = winit::window::WindowBuilder::new()
-        .with_title("ChromaViz")
+        .with_title(TITLE)
         .with_inner_size(winit::dpi::PhysicalSize::new(640, 480))
         .build(&event_loop)
         .unwrap();
@@ -45,19 +130,12 @@ fn viz() {
         size.width,
         size.height,
         wgpu::TextureFormat::Bgra8UnormSrgb,
-        ChromaSettings {
-            decay: 0.95,
-            particles: ParticleSettings {
-                gravity: (0.0, -4.0).into(),
-                frequencies: 32,
-                frequencies_spread: 1.0,
-                particles_per_second: 2000,
-                angular_spread: 2.0,
-                velocity_spread: 0.1,
-                size_range: 4.0..6.0,
-            },
-        },
-    );
+        settings,
+    )
+    .unwrap_or_else(|e| {
+        eprintln!("invalid settings: {}", e);
+        exit(1);
+    });
 
     // initialize size
     {
@@ -78,42 +156,70 @@ fn viz() {
 
     let mut swap_chain = device.create_swap_chain(&surface, &sc_desc);
 
+    let mut audio = Audio::new(buffer, renderer.settings().bands());
     let mut last_update_inst = Instant::now();
-    let start_inst = Instant::now();
+    let mut preset_watcher = options.preset.as_ref().map(FileWatcher::new);
+
+    audio.player.play();
 
     event_loop.run(move |event, _, control_flow| {
         *control_flow = ControlFlow::Poll;
 
         match event {
-            Event::MainEventsCleared => {
-                if last_update_inst.elapsed() >= Duration::from_millis(16) {
-                    let t = start_inst.elapsed().as_secs_f32();
-                    let phase = t;
-                    let global_height = (t * 4.0).sin() * 0.2 + 0.4;
-                    let freq_data: Vec<f32> = (0..32)
-                        .map(|f| (0.5 * f as f32 + phase).sin() * 0.2 + global_height)
-                        .map(|f| f.max(0.0).min(1.0))
-                        .collect();
-                    renderer.update(last_update_inst.elapsed(), &freq_data);
-
-                    let frame = match swap_chain.get_current_frame() {
-                        Ok(frame) => frame,
-                        Err(_) => {
-                            swap_chain = device.create_swap_chain(&surface, &sc_desc);
-                            swap_chain
-                                .get_current_frame()
-                                .expect("Failed to get next swapchain texture!")
+            Event::MainEventsCleared if last_update_inst.elapsed() >= Duration::from_millis(16) => {
+                match audio.player.state() {
+                    PlaybackState::Finished if options.looping => audio.player.play(),
+                    PlaybackState::Finished => *control_flow = ControlFlow::Exit,
+                    _ => {}
+                }
+
+                // edits to the preset apply live, broken ones leave the current settings alone
+                if let Some(watcher) = preset_watcher.as_mut() {
+                    if watcher.poll() {
+                        let reloaded = load_settings(Some(watcher.path()), options.seed)
+                            .and_then(|settings| {
+                                let bands = settings.bands();
+
+                                // sprite images are only loaded here
+                                renderer.set_settings(settings)?;
+                                audio.set_bands(bands);
+
+                                Ok(())
+                            });
+
+                        match reloaded {
+                            Ok(()) => {
+                                println!("reloaded {}", watcher.path().display());
+                                window.set_title(TITLE);
+                            }
+                            Err(e) => {
+                                eprintln!("couldn't reload preset: {}", e);
+                                window.set_title(&format!("{} - {}", TITLE, e));
+                            }
                         }
-                    };
+                    }
+                }
 
-                    let commands = renderer.render(&device, &frame.output.view);
+                let (delta, freq_data) = audio.update();
+                renderer.update(delta, freq_data);
 
-                    if !commands.is_empty() {
-                        queue.submit(commands);
+                let frame = match swap_chain.get_current_frame() {
+                    Ok(frame) => frame,
+                    Err(_) => {
+                        swap_chain = device.create_swap_chain(&surface, &sc_desc);
+                        swap_chain
+                            .get_current_frame()
+                            .expect("Failed to get next swapchain texture!")
                     }
+                };
 
-                    last_update_inst = Instant::now();
+                let commands = renderer.render(&device, &frame.output.view);
+
+                if !commands.is_empty() {
+                    queue.submit(commands);
                 }
+
+                last_update_inst = Instant::now();
             }
             Event::WindowEvent {
                 event: WindowEvent::Resized(size),
@@ -139,7 +245,47 @@ fn viz() {
 }
 
 fn main() {
-    let file = args().nth(1).map(|file| PathBuf::from(file)).or_else(|| {
+    let options = Options::parse(args().skip(1)).unwrap_or_else(|e| {
+        eprintln!("{}\n{}", e, USAGE);
+        exit(2);
+    });
+
+    if let Some(output) = &options.export {
+        let file = options.file.as_ref().unwrap_or_else(|| {
+            eprintln!("exporting needs an input file\n{}", USAGE);
+            exit(2);
+        });
+
+        let buffer = AudioBuffer::open(file).unwrap_or_else(|e| {
+            eprintln!("couldn't open {}: {}", file.display(), e);
+            exit(1);
+        });
+
+        let export = Export {
+            audio: file,
+            output,
+            format: options.format,
+            fps: options.fps,
+            size: options.size,
+        };
+
+        let mut settings = load_settings_or_exit(&options);
+
+        for (i, emitter) in settings.emitters.iter_mut().enumerate() {
+            // like `--seed 0`
+            emitter.particles.seed.get_or_insert(i as u64);
+        }
+
+        if let Err(e) = export::export(&buffer, settings, &export) {
+            eprintln!("export failed: {}", e);
+            exit(1);
+        }
+
+        return;
+    }
+
+    let settings = load_settings_or_exit(&options);
+    let file = options.file.clone().or_else(|| {
         let dialog = OpenSingleFile {
             dir: None,
             filter: Some(&["wav"]),
@@ -148,6 +294,16 @@ fn main() {
         dialog.show().ok().flatten()
     });
 
-    println!("playing {:?}", file);
-    viz();
+    let file = file.unwrap_or_else(|| {
+        eprintln!("no file selected\n{}", USAGE);
+        exit(2);
+    });
+
+    let buffer = AudioBuffer::open(&file).unwrap_or_else(|e| {
+        eprintln!("couldn't open {}: {}", file.display(), e);
+        exit(1);
+    });
+
+    println!("playing {}", file.display());
+    viz(buffer, options, settings);
 }
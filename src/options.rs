@@ -0,0 +1,113 @@
+use std::{path::PathBuf, str::FromStr};
+
+pub const USAGE: &str = "\
+usage: chroma [--loop] [--preset FILE] [--seed N] [FILE.wav]
+       chroma --export OUT [--format y4m|png|ffmpeg] [--fps N] [--size WxH]
+              [--preset FILE] [--seed N] FILE.wav";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// `video.y4m` and `audio.wav` in the output directory.
+    Y4m,
+    /// `frame_000000.png`, ... and `audio.wav` in the output directory.
+    Png,
+    /// Encoded by an external `ffmpeg` process into the output file.
+    Ffmpeg,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "y4m" => Ok(Format::Y4m),
+            "png" => Ok(Format::Png),
+            "ffmpeg" => Ok(Format::Ffmpeg),
+            _ => Err(format!("unknown format `{}`", s)),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Options {
+    pub file: Option<PathBuf>,
+    pub looping: bool,
+    /// A `.toml` or `.json` file with the visualizer settings.
+    pub preset: Option<PathBuf>,
+    /// Renders offline to this path instead of opening a window.
+    pub export: Option<PathBuf>,
+    pub format: Format,
+    pub fps: u32,
+    pub size: (u32, u32),
+    /// Seeds the particle emitters, the first with this and each next one with 1 more. Exports start
+    /// from 0 when it's not given, for the emitters the preset doesn't seed, so they are reproducible.
+    pub seed: Option<u64>,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            file: None,
+            looping: false,
+            preset: None,
+            export: None,
+            format: Format::Y4m,
+            fps: 60,
+            size: (640, 480),
+            seed: None,
+        }
+    }
+}
+
+impl Options {
+    pub fn parse<I: IntoIterator<Item = String>>(args: I) -> Result<Self, String> {
+        let mut options = Self::default();
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            let mut value = || {
+                args.next()
+                    .ok_or_else(|| format!("missing value for `{}`", arg))
+            };
+
+            match arg.as_str() {
+                "--loop" => options.looping = true,
+                "--preset" => options.preset = Some(value()?.into()),
+                "--export" => options.export = Some(value()?.into()),
+                "--format" => options.format = value()?.parse()?,
+                "--fps" => options.fps = parse_fps(&value()?)?,
+                "--size" => options.size = parse_size(&value()?)?,
+                "--seed" => options.seed = Some(parse_seed(&value()?)?),
+                flag if flag.starts_with("--") => {
+                    return Err(format!("unknown option `{}`", flag));
+                }
+                _ if options.file.is_none() => options.file = Some(arg.into()),
+                _ => return Err(format!("unexpected argument `{}`", arg)),
+            }
+        }
+
+        Ok(options)
+    }
+}
+
+fn parse_fps(s: &str) -> Result<u32, String> {
+    match s.parse() {
+        Ok(fps) if fps > 0 => Ok(fps),
+        _ => Err(format!("invalid frame rate `{}`", s)),
+    }
+}
+
+fn parse_size(s: &str) -> Result<(u32, u32), String> {
+    let size = s
+        .split_once('x')
+        .and_then(|(w, h)| Some((w.parse().ok()?, h.parse().ok()?)));
+
+    match size {
+        Some((w, h)) if w > 0 && h > 0 => Ok((w, h)),
+        _ => Err(format!("invalid size `{}`", s)),
+    }
+}
+
+fn parse_seed(s: &str) -> Result<u64, String> {
+    s.parse().map_err(|_| format!("invalid seed `{}`", s))
+}